@@ -1,35 +1,125 @@
 use avian3d::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
 use bevy::math::ops::{cos, sin};
-use bevy::{picking::pointer::PointerInteraction, prelude::*};
+use bevy::prelude::*;
+use bevy::render::{settings::WgpuSettings, RenderPlugin};
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use std::path::Path;
+use std::time::Duration;
 
+use core::camera::isometric_camera::cinematic::{
+    CameraSequence, PlayCameraSequence, SequenceMarkerReached,
+};
+use core::camera::isometric_camera::projection::IsometricProjection;
+use core::camera::isometric_camera::viewport::IsometricView;
+use core::camera::isometric_camera::zones::CameraZone;
+use core::camera::isometric_camera::{CameraManager, CameraMode, CameraYaw};
 use core::enemies::ant::AntSpawner;
-use core::game_world::{Ground, Wall};
+use core::game_world::{
+    wind::{WindFalloff, WindVolume},
+    Ground, Wall,
+};
+use core::input::input_manager::prompts::ActionPrompt;
+use core::input::input_manager::replay::{InputRecorder, InputReplay};
+use core::input::input_manager::touch::{TouchButton, VirtualStick, VirtualStickKnob};
 use core::input::input_manager::{
     button, motion, Action, InputManager, InputModeChanged, InputType,
 };
-use core::player::controller::PlayerSpawn;
+use core::player::controller::{
+    Player, PlayerSpawn, TOUCH_BUTTON_CORDYCEPT, TOUCH_BUTTON_FLOATY, TOUCH_STICK_MOVEMENT,
+};
 
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins,
-            core::CorePlugin,
-            MeshPickingPlugin,
-            WorldInspectorPlugin::new(),
-        ))
-        .add_systems(Startup, (setup, setup_walls, register_input))
-        .add_systems(Update, draw_cursor)
+    // --record <file> / --replay <file>, and --headless to run without a window or renderer
+    let args = std::env::args().collect::<Vec<_>>();
+    let headless = args.iter().any(|arg| arg == "--headless");
+
+    let mut app = App::new();
+    if headless {
+        app.add_plugins((
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    ..default()
+                })
+                .set(RenderPlugin {
+                    render_creation: WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }
+                    .into(),
+                    ..default()
+                })
+                .disable::<WinitPlugin>(),
+            ScheduleRunnerPlugin::run_loop(Duration::ZERO),
+        ));
+    } else {
+        app.add_plugins((DefaultPlugins, WorldInspectorPlugin::new()));
+    }
+    app.add_plugins((core::CorePlugin, MeshPickingPlugin))
+        .add_systems(
+            Startup,
+            (
+                setup,
+                setup_walls,
+                setup_prompts,
+                setup_touch_controls,
+                setup_sequence,
+                register_input,
+            ),
+        )
+        .add_systems(Update, (draw_cursor, toggle_projection, play_sequence))
         .add_observer(get_input_mode_change_trigger)
-        .run();
+        .add_observer(log_sequence_marker);
+
+    // a bad file is reported and the game starts without recording or replaying
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+    if let Some(path) = flag_value("--record") {
+        match InputRecorder::create(Path::new(path)) {
+            Ok(recorder) => {
+                app.insert_resource(recorder);
+            }
+            Err(err) => error!("not recording input to {path}: {err}"),
+        }
+    } else if let Some(path) = flag_value("--replay") {
+        match InputReplay::load(Path::new(path)) {
+            Ok(replay) => {
+                app.insert_resource(replay.with_exit_on_finish());
+            }
+            Err(err) => error!("not replaying input from {path}: {err}"),
+        }
+    }
+
+    app.run();
 }
 
 static ACTIVATE: Action = Action("activate");
 static SPAWN_SHROOM: Action = Action("spawn_shroom");
 static MOVEMENT: Action = Action("movement");
 static CAMERA: Action = Action("camera");
+static CURSOR: Action = Action("cursor");
+static AIM_STICK: Action = Action("aim_stick");
+static AIM: Action = Action("aim");
+static TOGGLE_PROJECTION: Action = Action("toggle_projection");
+static PLAY_SEQUENCE: Action = Action("play_sequence");
 
 fn register_input(mut im: ResMut<InputManager>) {
+    im.register_action_button(
+        TOGGLE_PROJECTION,
+        vec![button::Variant::Keyboard(KeyCode::KeyO)],
+    );
+    im.register_action_button(
+        PLAY_SEQUENCE,
+        vec![button::Variant::Keyboard(KeyCode::KeyI)],
+    );
+
     im.register_action_button(
         ACTIVATE,
         vec![
@@ -42,7 +132,7 @@ fn register_input(mut im: ResMut<InputManager>) {
         SPAWN_SHROOM,
         vec![
             button::Variant::Keyboard(KeyCode::Space),
-            button::Variant::Gamepad(GamepadButton::North),
+            button::Variant::Gamepad(GamepadButton::West),
         ],
     );
 
@@ -74,10 +164,11 @@ fn register_input(mut im: ResMut<InputManager>) {
             motion::Entry {
                 input_type: InputType::Keyboard,
                 relations: vec![
-                    motion::Relation::KeyCode(KeyCode::KeyK, motion::Axis::PosY),
-                    motion::Relation::KeyCode(KeyCode::KeyJ, motion::Axis::NegY),
-                    motion::Relation::KeyCode(KeyCode::KeyL, motion::Axis::PosX),
-                    motion::Relation::KeyCode(KeyCode::KeyH, motion::Axis::NegX),
+                    // not K/J, those are the floaty and cordycept abilities
+                    motion::Relation::KeyCode(KeyCode::ArrowUp, motion::Axis::PosY),
+                    motion::Relation::KeyCode(KeyCode::ArrowDown, motion::Axis::NegY),
+                    motion::Relation::KeyCode(KeyCode::ArrowRight, motion::Axis::PosX),
+                    motion::Relation::KeyCode(KeyCode::ArrowLeft, motion::Axis::NegX),
                 ],
             },
             motion::Entry {
@@ -93,6 +184,27 @@ fn register_input(mut im: ResMut<InputManager>) {
             },
         ],
     );
+    im.register_action_cursor(CURSOR);
+    im.register_action_motion(
+        AIM_STICK,
+        vec![motion::Entry {
+            input_type: InputType::Gamepad,
+            relations: vec![
+                motion::Relation::GamepadAxis(GamepadAxis::RightStickY, motion::Axis::Y),
+                motion::Relation::GamepadAxis(GamepadAxis::RightStickX, motion::Axis::X),
+            ],
+        }],
+    );
+    im.register_action_aim(AIM, CURSOR, AIM_STICK);
+
+    // finer camera control near the stick center
+    im.set_motion_settings(
+        CAMERA,
+        motion::MotionSettings {
+            curve: motion::ResponseCurve::Quadratic,
+            ..default()
+        },
+    );
 }
 
 fn get_input_mode_change_trigger(trigger: Trigger<InputModeChanged>) {
@@ -116,6 +228,21 @@ fn setup(
         Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
         Ground,
     ));
+    // the character controller stands on static colliders, the top is level with the ground mesh
+    commands.spawn((
+        Collider::cylinder(4.0, 0.1),
+        RigidBody::Static,
+        Transform::from_xyz(0., -0.05, 0.),
+    ));
+
+    // minimap in the top right corner, drawn after the main camera
+    commands.spawn((
+        IsometricView::new(CameraMode::Minimap).with_viewport(Rect::new(0.75, 0., 1., 0.25)),
+        Camera {
+            order: 1,
+            ..default()
+        },
+    ));
 
     // light
     commands.spawn((
@@ -134,6 +261,113 @@ fn setup(
         Transform::from_xyz(3., 1., 0.),
         AntSpawner::new(2),
     ));
+
+    // updraft to balloon up on
+    commands.spawn((
+        WindVolume {
+            direction: Dir3::Y,
+            strength: 6.,
+            falloff: WindFalloff::Directional { length: 3. },
+            ..default()
+        },
+        Collider::cylinder(1., 6.),
+        Transform::from_xyz(2.5, 3., 2.5),
+    ));
+
+    // camera swings around and pulls in on the far side of the ground
+    commands.spawn((
+        CameraZone {
+            yaw: Some(135.),
+            pitch: Some(-60.),
+            arm_length: Some(12.),
+            ..default()
+        },
+        Collider::cuboid(4., 4., 4.),
+        Transform::from_xyz(-2., 1., -2.),
+    ));
+}
+
+// follows the input mode, e.g. "Press E to activate" / "Press A/South to activate"
+fn setup_prompts(mut commands: Commands) {
+    commands.spawn((
+        ActionPrompt::new(ACTIVATE).with_template("Press {} to activate"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.),
+            left: Val::Px(12.),
+            ..default()
+        },
+    ));
+}
+
+#[derive(Resource)]
+struct RitualReveal(Handle<CameraSequence>);
+
+fn setup_sequence(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(RitualReveal(
+        asset_server.load("sequences/ritual_reveal.sequence.ron"),
+    ));
+}
+
+fn play_sequence(im: Res<InputManager>, reveal: Res<RitualReveal>, mut commands: Commands) {
+    if im.is_action_just_pressed(PLAY_SEQUENCE) {
+        commands.trigger(PlayCameraSequence(reveal.0.clone()));
+    }
+}
+
+fn log_sequence_marker(trigger: Trigger<SequenceMarkerReached>) {
+    info!("sequence marker: {}", trigger.event().0);
+}
+
+// only visible in touch mode
+fn setup_touch_controls(mut commands: Commands) {
+    let radius = 60.;
+    commands
+        .spawn((
+            VirtualStick {
+                id: TOUCH_STICK_MOVEMENT,
+                radius,
+            },
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(40.),
+                bottom: Val::Px(40.),
+                width: Val::Px(radius * 2.),
+                height: Val::Px(radius * 2.),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(1., 1., 1., 0.15)),
+            BorderRadius::MAX,
+        ))
+        .with_child((
+            VirtualStickKnob,
+            Node {
+                width: Val::Px(radius * 0.8),
+                height: Val::Px(radius * 0.8),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(1., 1., 1., 0.4)),
+            BorderRadius::MAX,
+        ));
+
+    for (i, id) in [TOUCH_BUTTON_FLOATY, TOUCH_BUTTON_CORDYCEPT]
+        .into_iter()
+        .enumerate()
+    {
+        commands.spawn((
+            TouchButton(id),
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(40. + i as f32 * 100.),
+                bottom: Val::Px(40. + i as f32 * 40.),
+                width: Val::Px(80.),
+                height: Val::Px(80.),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(1., 1., 1., 0.25)),
+            BorderRadius::MAX,
+        ));
+    }
 }
 
 pub fn setup_walls(
@@ -170,29 +404,38 @@ pub fn setup_walls(
 }
 
 fn draw_cursor(
-    pointers: Query<&PointerInteraction>,
+    im: Res<InputManager>,
+    yaw: Res<CameraYaw>,
+    player: Single<&Transform, With<Player>>,
     mut gizmos: Gizmos,
-    ground_entity: Query<Entity, With<Ground>>,
 ) {
     // draw circle just above ground plane
-    for (entity, point, normal) in pointers
-        .iter()
-        .filter_map(|interaction| interaction.get_nearest_hit())
-        .filter_map(|(entity, hit)| {
-            hit.position
-                .zip(hit.normal)
-                .map(|(position, normal)| (entity, position, normal))
-        })
-    {
-        if ground_entity.contains(*entity) {
-            gizmos.circle(
-                Isometry3d::new(
-                    point + normal * 0.01,
-                    Quat::from_rotation_arc(Vec3::Z, normal),
-                ),
-                0.2,
-                Color::WHITE,
-            );
-        }
+    if let Some(point) = im.get_cursor_world(CURSOR) {
+        gizmos.circle(
+            Isometry3d::new(
+                point + Vec3::Y * 0.01,
+                Quat::from_rotation_arc(Vec3::Z, Vec3::Y),
+            ),
+            0.2,
+            Color::WHITE,
+        );
+    }
+
+    if let Some(aim) = im.get_aim_direction(AIM, player.translation, yaw.get()) {
+        let from = player.translation + Vec3::Y * 0.01;
+        gizmos.arrow(from, from + aim * 1.5, Color::WHITE);
     }
 }
+
+fn toggle_projection(im: Res<InputManager>, mut camera_manager: ResMut<CameraManager>) {
+    if !im.is_action_just_pressed(TOGGLE_PROJECTION) {
+        return;
+    }
+    let projection = match camera_manager.get_projection(CameraMode::Game) {
+        IsometricProjection::Perspective => IsometricProjection::Orthographic {
+            visible_height: 12.,
+        },
+        IsometricProjection::Orthographic { .. } => IsometricProjection::Perspective,
+    };
+    camera_manager.set_projection(CameraMode::Game, projection);
+}
@@ -1,4 +1,22 @@
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{prelude::*, transform::TransformSystem, utils::HashMap};
+
+pub mod cinematic;
+pub mod editor;
+pub mod follow;
+pub mod occlusion;
+pub mod photo;
+pub mod projection;
+pub mod shake;
+pub mod spring_arm;
+pub mod viewport;
+pub mod yaw_snap;
+pub mod zones;
+
+use follow::CameraFollow;
+use projection::IsometricProjection;
+use shake::ShakeSettings;
+use viewport::IsometricView;
+use yaw_snap::YawSnap;
 
 const UP: Dir3 = Dir3::Y;
 
@@ -7,11 +25,57 @@ impl Plugin for IsometricCameraPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CameraManager>()
             .init_resource::<CameraYaw>()
-            .add_systems(Startup, setup)
-            .add_systems(Update, update);
+            .add_systems(
+                Startup,
+                (
+                    setup,
+                    yaw_snap::register_input,
+                    editor::register_input,
+                    photo::register_input,
+                ),
+            )
+            .init_asset::<cinematic::CameraSequence>()
+            .init_asset_loader::<cinematic::CameraSequenceLoader>()
+            .add_observer(shake::add_trauma)
+            .add_observer(cinematic::play_sequence_asset)
+            .add_systems(
+                Update,
+                (
+                    yaw_snap::process_input,
+                    editor::toggle_editor,
+                    editor::process_input,
+                    photo::toggle_photo_mode,
+                    photo::process_input,
+                    photo::hide_ui.after(photo::toggle_photo_mode),
+                ),
+            )
+            // after gameplay moved the target this frame, before the camera transform propagates
+            .add_systems(
+                PostUpdate,
+                (
+                    yaw_snap::update_yaw_rotation,
+                    follow::follow_target,
+                    zones::update_camera_zones,
+                    spring_arm::update_spring_arm,
+                    cinematic::update_sequence,
+                    shake::update_shake,
+                    update,
+                    projection::update_projection,
+                    photo::apply_photo_effects,
+                    viewport::update_viewports,
+                    occlusion::fade_occluders,
+                )
+                    .chain()
+                    .before(TransformSystem::TransformPropagate),
+            );
     }
 }
 
+/**
+ * Settled yaw of the current camera in radians, the angle input directions are relative to.
+ * While the camera snaps to a new yaw this is already the angle it is rotating towards.
+ * There is only the one, split views and the minimap don't steer with their own yaw
+ */
 #[derive(Resource, Default)]
 pub struct CameraYaw(f32);
 impl CameraYaw {
@@ -27,12 +91,22 @@ impl CameraYaw {
 pub enum CameraMode {
     Game,
     Editor,
+    // overhead camera for a minimap or picture-in-picture
+    Minimap,
+    // extra game cameras for split-screen, see add_split_camera
+    Split(u8),
+    // driven by camera sequences, see play_sequence
+    Cinematic,
+    // free camera of photo mode, starts where the previous camera was
+    Photo,
 }
 
 #[derive(Debug, Resource)]
 pub struct CameraManager {
     current_mode: CameraMode,
     cameras: HashMap<CameraMode, IsometricCamera>,
+    sequence: Option<cinematic::Playback>,
+    photo: photo::PhotoMode,
 }
 
 impl Default for CameraManager {
@@ -41,9 +115,64 @@ impl Default for CameraManager {
         Self {
             current_mode: default_mode,
             cameras: HashMap::from([
-                (default_mode, IsometricCamera::default()),
-                (CameraMode::Editor, IsometricCamera::default()),
+                (
+                    default_mode,
+                    IsometricCamera {
+                        follow: Some(CameraFollow::default()),
+                        yaw_snap: Some(YawSnap::default()),
+                        ..default()
+                    },
+                ),
+                (
+                    CameraMode::Editor,
+                    IsometricCamera {
+                        arm_collision: false,
+                        zones: false,
+                        ..default()
+                    },
+                ),
+                (
+                    CameraMode::Minimap,
+                    IsometricCamera {
+                        angle_yaw: 0.,
+                        angle_pitch: -89.,
+                        spring_arm_length: 40.,
+                        arm_length: 40.,
+                        arm_collision: false,
+                        zones: false,
+                        fade_occluders: false,
+                        follow: Some(CameraFollow {
+                            smooth_time: 0.1,
+                            look_ahead: 0.,
+                            dead_zone: Vec2::ZERO,
+                        }),
+                        projection: IsometricProjection::Orthographic {
+                            visible_height: 30.,
+                        },
+                        ..default()
+                    },
+                ),
+                (
+                    CameraMode::Cinematic,
+                    IsometricCamera {
+                        arm_collision: false,
+                        zones: false,
+                        fade_occluders: false,
+                        ..default()
+                    },
+                ),
+                (
+                    CameraMode::Photo,
+                    IsometricCamera {
+                        arm_collision: false,
+                        zones: false,
+                        fade_occluders: false,
+                        ..default()
+                    },
+                ),
             ]),
+            sequence: None,
+            photo: default(),
         }
     }
 }
@@ -66,7 +195,7 @@ impl CameraManager {
     }
 
     pub fn get_camera_rotation(&self) -> Quat {
-        Quat::from_rotation_y(self.get().angle_yaw)
+        Quat::from_rotation_y(self.get().angle_yaw.to_radians())
     }
 
     pub fn get_camera_forward_horizontal(&self) -> Vec3 {
@@ -95,6 +224,17 @@ impl CameraManager {
     pub fn rotate_camera_pitch(&mut self, rotation: f32) {
         self.get_mut().rotate_camera_pitch(rotation)
     }
+
+    /// None stops following, the pivot stays where it is
+    pub fn set_follow(&mut self, mode: CameraMode, follow: Option<CameraFollow>) {
+        let camera = self.cameras.get_mut(&mode).unwrap();
+        camera.follow = follow;
+        camera.follow_state = default();
+    }
+
+    pub fn get_follow(&self, mode: CameraMode) -> Option<CameraFollow> {
+        self.cameras.get(&mode).unwrap().follow
+    }
 }
 
 #[derive(Debug, Component)]
@@ -103,6 +243,23 @@ pub struct IsometricCamera {
     angle_yaw: f32,
     angle_pitch: f32,
     spring_arm_length: f32,
+    // spring_arm_length unless something blocks the arm
+    arm_length: f32,
+    arm_collision: bool,
+    // index of the CameraTarget followed and used for zones and occlusion
+    target: u8,
+    zones: bool,
+    fade_occluders: bool,
+    follow: Option<CameraFollow>,
+    follow_state: follow::FollowState,
+    yaw_snap: Option<YawSnap>,
+    yaw_rotation: Option<yaw_snap::YawRotation>,
+    projection: IsometricProjection,
+    // vertical field of view of perspective projections, in degrees
+    fov: f32,
+    zone: zones::ZoneState,
+    shake_settings: ShakeSettings,
+    shake: shake::ShakeState,
 }
 
 impl Default for IsometricCamera {
@@ -112,6 +269,20 @@ impl Default for IsometricCamera {
             angle_yaw: 45., // angles in degrees
             angle_pitch: -45.,
             spring_arm_length: 20.,
+            arm_length: 20.,
+            arm_collision: true,
+            target: 0,
+            zones: true,
+            fade_occluders: true,
+            follow: None,
+            follow_state: default(),
+            yaw_snap: None,
+            yaw_rotation: None,
+            projection: IsometricProjection::Perspective,
+            fov: 45.,
+            zone: default(),
+            shake_settings: default(),
+            shake: default(),
         }
     }
 }
@@ -127,6 +298,10 @@ impl IsometricCamera {
     }
 
     fn rotate_camera_yaw(&mut self, rotation: f32) {
+        // snapping cameras only turn in steps, see snap_yaw
+        if self.yaw_snap.is_some() {
+            return;
+        }
         self.angle_yaw += rotation
     }
 
@@ -135,9 +310,16 @@ impl IsometricCamera {
         self.angle_pitch = self.angle_pitch.clamp(-89., 89.)
     }
 
-    fn get_camera_transform(&self) -> Transform {
+    // pivot as seen by the camera, moved by camera zones and rails
+    fn view_pivot(&self) -> Vec3 {
+        self.pivot + self.view().pivot_offset
+    }
+
+    // unit vector from the pivot towards the camera
+    fn arm_direction(&self) -> Vec3 {
+        let view = self.view();
         let mut transform = Transform::from_translation(self.pivot);
-        transform.rotate_y(self.angle_yaw.to_radians());
+        transform.rotate_y(view.yaw.to_radians());
         let axis = {
             let forward = transform.forward();
             if let Ok(dir) = Dir3::new(UP.cross(forward.as_vec3()).normalize()) {
@@ -146,17 +328,24 @@ impl IsometricCamera {
                 Dir3::X
             }
         };
-        transform.rotate_axis(axis, self.angle_pitch.to_radians());
-
-        let pos = self.pivot + (transform.forward() * self.spring_arm_length);
+        transform.rotate_axis(axis, view.pitch.to_radians());
+        transform.forward().as_vec3()
+    }
 
-        transform = transform.with_translation(pos);
-        transform.looking_at(self.pivot, UP)
+    fn get_camera_transform(&self) -> Transform {
+        let pivot = self.view_pivot();
+        let pos = pivot + self.arm_direction() * self.arm_length;
+        Transform::from_translation(pos).looking_at(pivot, UP)
     }
 }
 
 fn setup(mut commands: Commands, camera_manager: Res<CameraManager>) {
-    commands.spawn((Camera3d::default(), camera_manager.get_camera_transform()));
+    // UI is laid out on the main view, not on whichever extra view has the highest order
+    commands.spawn((
+        IsometricView::default(),
+        camera_manager.get_camera_transform(),
+        IsDefaultUiCamera,
+    ));
     println!(
         "camera transform: {:?}",
         camera_manager.get_camera_transform()
@@ -166,8 +355,12 @@ fn setup(mut commands: Commands, camera_manager: Res<CameraManager>) {
 fn update(
     camera_manager: Res<CameraManager>,
     mut camera_yaw: ResMut<CameraYaw>,
-    mut transform: Single<&mut Transform, With<Camera>>,
+    mut views: Query<(&IsometricView, &mut Transform)>,
 ) {
-    **transform = camera_manager.get_camera_transform();
-    camera_yaw.set(camera_manager.get_yaw());
+    for (view, mut transform) in views.iter_mut() {
+        if let Some(camera) = camera_manager.get_view(view) {
+            *transform = camera.apply_shake(camera.get_camera_transform());
+        }
+    }
+    camera_yaw.set(camera_manager.get_settled_yaw().to_radians());
 }
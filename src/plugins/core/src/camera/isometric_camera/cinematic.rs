@@ -0,0 +1,327 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, io};
+
+use super::{CameraManager, CameraMode, IsometricCamera};
+use crate::input::input_manager::{contexts::InputContext, InputManager};
+
+/**
+ * Camera timeline for cutscenes, loaded from .sequence.ron files.
+ * The camera blends from where it was to the first keyframe, and back to the previous camera after the last
+ */
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct CameraSequence {
+    pub keyframes: Vec<CameraKeyframe>,
+    #[serde(default)]
+    pub markers: Vec<SequenceMarker>,
+    // seconds to blend back to the camera that was active before the sequence
+    #[serde(default = "default_blend_out")]
+    pub blend_out: f32,
+}
+
+fn default_blend_out() -> f32 {
+    1.
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    // seconds from the start of the sequence
+    pub time: f32,
+    pub pivot: Vec3,
+    // degrees, same as the camera angles, not wrapped so a keyframe can spin past 360
+    pub yaw: f32,
+    pub pitch: f32,
+    pub arm_length: f32,
+    // vertical field of view in degrees
+    pub fov: f32,
+    // easing of the segment from the previous keyframe to this one
+    #[serde(default = "default_ease")]
+    pub ease: EaseFunction,
+}
+
+fn default_ease() -> EaseFunction {
+    EaseFunction::CubicInOut
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceMarker {
+    pub time: f32,
+    pub name: String,
+}
+
+/// Plays a loaded CameraSequence, see CameraManager::play_sequence
+#[derive(Event, Debug, Clone)]
+pub struct PlayCameraSequence(pub Handle<CameraSequence>);
+
+/// Triggered when playback passes a marker, with the marker name
+#[derive(Event, Debug, Clone)]
+pub struct SequenceMarkerReached(pub String);
+
+/// Triggered when the camera is back with the previous camera after a sequence
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SequenceFinished;
+
+#[derive(Debug)]
+pub enum SequenceLoadError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for SequenceLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "camera sequence file: {err}"),
+            Self::Parse(err) => write!(f, "camera sequence file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SequenceLoadError {}
+
+#[derive(Default)]
+pub(super) struct CameraSequenceLoader;
+
+impl AssetLoader for CameraSequenceLoader {
+    type Asset = CameraSequence;
+    type Settings = ();
+    type Error = SequenceLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<CameraSequence, SequenceLoadError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(SequenceLoadError::Io)?;
+        ron::de::from_bytes(&bytes).map_err(SequenceLoadError::Parse)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sequence.ron"]
+    }
+}
+
+// what the camera shows at one point of the sequence
+#[derive(Debug, Clone, Copy)]
+struct Shot {
+    pivot: Vec3,
+    yaw: f32,
+    pitch: f32,
+    arm_length: f32,
+    fov: f32,
+}
+
+impl Shot {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            pivot: self.pivot.lerp(other.pivot, t),
+            yaw: self.yaw.lerp(other.yaw, t),
+            pitch: self.pitch.lerp(other.pitch, t),
+            arm_length: self.arm_length.lerp(other.arm_length, t),
+            fov: self.fov.lerp(other.fov, t),
+        }
+    }
+
+    // yaw moved by whole turns to be closest to reference, so blends take the short way
+    fn near_yaw(mut self, reference: f32) -> Self {
+        self.yaw = reference + (self.yaw - reference + 180.).rem_euclid(360.) - 180.;
+        self
+    }
+}
+
+impl From<&CameraKeyframe> for Shot {
+    fn from(keyframe: &CameraKeyframe) -> Self {
+        Self {
+            pivot: keyframe.pivot,
+            yaw: keyframe.yaw,
+            pitch: keyframe.pitch,
+            arm_length: keyframe.arm_length,
+            fov: keyframe.fov,
+        }
+    }
+}
+
+impl CameraSequence {
+    pub fn duration(&self) -> f32 {
+        self.keyframes
+            .last()
+            .map(|keyframe| keyframe.time)
+            .unwrap_or(0.)
+    }
+
+    fn sample(&self, time: f32) -> Option<Shot> {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time);
+        match next {
+            Some(0) => self.keyframes.first().map(Shot::from),
+            Some(i) => {
+                let (from, to) = (&self.keyframes[i - 1], &self.keyframes[i]);
+                let t = (time - from.time) / (to.time - from.time).max(f32::EPSILON);
+                let eased = EasingCurve::new(0., 1., to.ease).sample_clamped(t);
+                Some(Shot::from(from).lerp(&Shot::from(to), eased))
+            }
+            None => self.keyframes.last().map(Shot::from),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct Playback {
+    sequence: CameraSequence,
+    elapsed: f32,
+    previous_mode: CameraMode,
+    // markers before this one were already triggered
+    next_marker: usize,
+    // the cinematic input context is pushed by the first update
+    started: bool,
+}
+
+impl IsometricCamera {
+    fn shot(&self) -> Shot {
+        let view = self.view();
+        Shot {
+            pivot: self.view_pivot(),
+            yaw: view.yaw,
+            pitch: view.pitch,
+            arm_length: self.arm_length,
+            fov: self.fov,
+        }
+    }
+
+    fn set_shot(&mut self, shot: Shot) {
+        self.pivot = shot.pivot;
+        self.angle_yaw = shot.yaw;
+        self.angle_pitch = shot.pitch;
+        self.spring_arm_length = shot.arm_length;
+        self.arm_length = shot.arm_length;
+        self.fov = shot.fov;
+    }
+}
+
+impl CameraManager {
+    /**
+     * Plays the sequence on the cinematic camera, blocking player input until it is done.
+     * A sequence already playing is replaced, and still returns to the camera from before it
+     */
+    pub fn play_sequence(&mut self, mut sequence: CameraSequence) {
+        if sequence.keyframes.is_empty() {
+            warn!("camera sequence without keyframes");
+            return;
+        }
+        sequence.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        sequence.markers.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        // start from the current view when the first keyframe is not at the start
+        let first = sequence.keyframes[0];
+        if first.time > 0. {
+            let start = self.get().shot().near_yaw(first.yaw);
+            sequence.keyframes.insert(
+                0,
+                CameraKeyframe {
+                    time: 0.,
+                    pivot: start.pivot,
+                    yaw: start.yaw,
+                    pitch: start.pitch,
+                    arm_length: start.arm_length,
+                    fov: start.fov,
+                    ease: first.ease,
+                },
+            );
+        }
+
+        let (previous_mode, started) = match self.sequence.take() {
+            Some(playback) => (playback.previous_mode, playback.started),
+            None => (self.current_mode, false),
+        };
+        self.sequence = Some(Playback {
+            sequence,
+            elapsed: 0.,
+            previous_mode,
+            next_marker: 0,
+            started,
+        });
+        self.set_mode(CameraMode::Cinematic);
+    }
+
+    pub fn is_sequence_playing(&self) -> bool {
+        self.sequence.is_some()
+    }
+}
+
+pub(super) fn play_sequence_asset(
+    trigger: Trigger<PlayCameraSequence>,
+    sequences: Res<Assets<CameraSequence>>,
+    mut camera_manager: ResMut<CameraManager>,
+) {
+    let Some(sequence) = sequences.get(&trigger.event().0) else {
+        warn!("camera sequence is not loaded yet");
+        return;
+    };
+    camera_manager.play_sequence(sequence.clone());
+}
+
+pub(super) fn update_sequence(
+    mut camera_manager: ResMut<CameraManager>,
+    mut im: ResMut<InputManager>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let camera_manager = &mut *camera_manager;
+    let Some(playback) = &mut camera_manager.sequence else {
+        return;
+    };
+    if !playback.started {
+        im.push_context(InputContext::CINEMATIC, true);
+        playback.started = true;
+    }
+    playback.elapsed += time.delta_secs();
+
+    while let Some(marker) = playback.sequence.markers.get(playback.next_marker) {
+        if marker.time > playback.elapsed {
+            break;
+        }
+        commands.trigger(SequenceMarkerReached(marker.name.clone()));
+        playback.next_marker += 1;
+    }
+
+    let duration = playback.sequence.duration();
+    let Some(shot) = playback.sequence.sample(playback.elapsed) else {
+        return;
+    };
+    let blend = (playback.elapsed - duration) / playback.sequence.blend_out.max(f32::EPSILON);
+    let shot = if blend <= 0. {
+        shot
+    } else {
+        // the previous camera kept following while the sequence played, blend to where it is now
+        let previous = camera_manager
+            .cameras
+            .get(&playback.previous_mode)
+            .unwrap()
+            .shot()
+            .near_yaw(shot.yaw);
+        let eased = EasingCurve::new(0., 1., EaseFunction::CubicInOut).sample_clamped(blend);
+        shot.lerp(&previous, eased)
+    };
+    camera_manager
+        .cameras
+        .get_mut(&CameraMode::Cinematic)
+        .unwrap()
+        .set_shot(shot);
+
+    if blend >= 1. {
+        let previous_mode = playback.previous_mode;
+        camera_manager.sequence = None;
+        camera_manager.set_mode(previous_mode);
+        im.remove_context(InputContext::CINEMATIC);
+        commands.trigger(SequenceFinished);
+    }
+}
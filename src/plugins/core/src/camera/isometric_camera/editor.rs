@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+
+use super::{CameraManager, CameraMode};
+use crate::input::input_manager::{
+    button, contexts::InputContext, motion, Action, InputManager, InputType,
+};
+
+// the same key enters from gameplay and leaves from the editor, the editor context blocks everything below it
+static ENTER_EDITOR: Action = Action("enter_editor_camera");
+static EXIT_EDITOR: Action = Action("exit_editor_camera");
+static EDITOR_ORBIT: Action = Action("editor_camera_orbit");
+static EDITOR_PAN: Action = Action("editor_camera_pan");
+static EDITOR_LOOK: Action = Action("editor_camera_look");
+static EDITOR_ZOOM: Action = Action("editor_camera_zoom");
+static EDITOR_FLY: Action = Action("editor_camera_fly");
+static EDITOR_FLY_VERTICAL: Action = Action("editor_camera_fly_vertical");
+
+const TOGGLE_KEY: KeyCode = KeyCode::F1;
+// degrees per pixel
+const ORBIT_SPEED: f32 = 0.3;
+// world units per pixel
+const PAN_SPEED: f32 = 0.02;
+// world units per scroll line
+const ZOOM_SPEED: f32 = 1.;
+// world units per second
+const FLY_SPEED: f32 = 10.;
+
+impl CameraManager {
+    /// Moves the pivot of the current camera in its view plane, x is right and y is up
+    pub fn pan_camera(&mut self, movement: Vec2) {
+        let transform = self.get_camera_transform();
+        let movement = transform.right() * movement.x + transform.up() * movement.y;
+        self.move_camera_global(movement);
+    }
+}
+
+pub(super) fn register_input(mut im: ResMut<InputManager>) {
+    im.set_action_context(ENTER_EDITOR, InputContext::GLOBAL);
+    im.register_action_button(ENTER_EDITOR, vec![button::Variant::Keyboard(TOGGLE_KEY)]);
+
+    for action in [
+        EXIT_EDITOR,
+        EDITOR_ORBIT,
+        EDITOR_PAN,
+        EDITOR_LOOK,
+        EDITOR_ZOOM,
+        EDITOR_FLY,
+        EDITOR_FLY_VERTICAL,
+    ] {
+        im.set_action_context(action, InputContext::EDITOR);
+    }
+    im.register_action_button(EXIT_EDITOR, vec![button::Variant::Keyboard(TOGGLE_KEY)]);
+    im.register_action_button(
+        EDITOR_ORBIT,
+        vec![button::Variant::Mouse(MouseButton::Right)],
+    );
+    im.register_action_button(
+        EDITOR_PAN,
+        vec![button::Variant::Mouse(MouseButton::Middle)],
+    );
+    im.register_action_motion(
+        EDITOR_LOOK,
+        vec![motion::Entry {
+            input_type: InputType::Mouse,
+            relations: vec![motion::Relation::Mouse(1.)],
+        }],
+    );
+    im.register_action_motion(
+        EDITOR_ZOOM,
+        vec![motion::Entry {
+            input_type: InputType::Mouse,
+            relations: vec![motion::Relation::MouseWheel(1.)],
+        }],
+    );
+    im.register_action_motion(
+        EDITOR_FLY,
+        vec![motion::Entry {
+            input_type: InputType::Keyboard,
+            relations: vec![
+                motion::Relation::KeyCode(KeyCode::KeyW, motion::Axis::PosY),
+                motion::Relation::KeyCode(KeyCode::KeyS, motion::Axis::NegY),
+                motion::Relation::KeyCode(KeyCode::KeyD, motion::Axis::PosX),
+                motion::Relation::KeyCode(KeyCode::KeyA, motion::Axis::NegX),
+            ],
+        }],
+    );
+    im.register_action_motion(
+        EDITOR_FLY_VERTICAL,
+        vec![motion::Entry {
+            input_type: InputType::Keyboard,
+            relations: vec![
+                motion::Relation::KeyCode(KeyCode::KeyE, motion::Axis::PosY),
+                motion::Relation::KeyCode(KeyCode::KeyQ, motion::Axis::NegY),
+            ],
+        }],
+    );
+}
+
+/**
+ * Switches between the game and the editor camera.
+ * The editor context is exclusive, so the player and everything else below it gets no input.
+ * Photo mode also flies with the editor context, exiting is left to it there
+ */
+pub(super) fn toggle_editor(
+    mut im: ResMut<InputManager>,
+    mut camera_manager: ResMut<CameraManager>,
+) {
+    if im.is_action_just_pressed(ENTER_EDITOR) {
+        camera_manager.set_mode(CameraMode::Editor);
+        im.push_context(InputContext::EDITOR, true);
+    } else if im.is_action_just_pressed(EXIT_EDITOR)
+        && camera_manager.current_mode == CameraMode::Editor
+    {
+        im.remove_context(InputContext::EDITOR);
+        camera_manager.set_mode(CameraMode::Game);
+    }
+}
+
+pub(super) fn process_input(
+    im: Res<InputManager>,
+    mut camera_manager: ResMut<CameraManager>,
+    // real time, photo mode flies with these controls while the simulation is paused
+    time: Res<Time<Real>>,
+) {
+    if !im.is_context_active(InputContext::EDITOR) {
+        return;
+    }
+
+    let look = im.get_motion(EDITOR_LOOK).raw_vec2();
+    if im.is_action_pressed(EDITOR_ORBIT) {
+        camera_manager.rotate_camera_yaw(-look.x * ORBIT_SPEED);
+        camera_manager.rotate_camera_pitch(look.y * ORBIT_SPEED);
+    } else if im.is_action_pressed(EDITOR_PAN) {
+        camera_manager.pan_camera(Vec2::new(-look.x, look.y) * PAN_SPEED);
+    }
+
+    let zoom = im.get_motion(EDITOR_ZOOM).raw_vec2().y;
+    if zoom != 0. {
+        camera_manager.zoom_camera(-zoom * ZOOM_SPEED);
+    }
+
+    // fly relative to where the editor camera looks, like the player moves relative to the game camera
+    let yaw = camera_manager.get_yaw().to_radians();
+    let vertical = im.get_motion(EDITOR_FLY_VERTICAL).raw_vec2().y;
+    let fly = im
+        .get_motion(EDITOR_FLY)
+        .get_motion_opt_y(yaw)
+        .unwrap_or_default()
+        + Vec3::Y * vertical;
+    if fly != Vec3::ZERO {
+        camera_manager.move_camera_global(fly * FLY_SPEED * time.delta_secs());
+    }
+}
@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+
+use super::CameraManager;
+
+/**
+ * Entity the following cameras keep in view, e.g. the Player.
+ * The index picks which cameras follow it, 0 is the Game camera and split cameras have their own
+ */
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CameraTarget(pub u8);
+
+#[derive(Debug, Clone, Copy)]
+pub struct CameraFollow {
+    // roughly the seconds the pivot needs to catch up, critically damped so it never overshoots
+    pub smooth_time: f32,
+    // the pivot leads the target by its horizontal velocity times this, in seconds
+    pub look_ahead: f32,
+    // half extents of a rect around the pivot, aligned with the camera yaw,
+    // the target moves freely inside it without the pivot following
+    pub dead_zone: Vec2,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            smooth_time: 0.25,
+            look_ahead: 0.3,
+            dead_zone: Vec2::new(0.5, 0.5),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct FollowState {
+    velocity: Vec3,
+    // None until the first frame of following, the pivot snaps to the target then
+    last_target: Option<Vec3>,
+}
+
+// critically damped spring towards target, velocity is kept between frames
+fn smooth_damp(
+    current: Vec3,
+    target: Vec3,
+    velocity: &mut Vec3,
+    smooth_time: f32,
+    dt: f32,
+) -> Vec3 {
+    let omega = 2. / smooth_time.max(0.0001);
+    let x = omega * dt;
+    // approximation of exp(-x) that is stable for large steps
+    let decay = 1. / (1. + x + 0.48 * x * x + 0.235 * x * x * x);
+    let offset = current - target;
+    let temp = (*velocity + omega * offset) * dt;
+    *velocity = (*velocity - omega * temp) * decay;
+    target + (offset + temp) * decay
+}
+
+pub(super) fn target_position(
+    targets: &Query<(&Transform, &CameraTarget)>,
+    index: u8,
+) -> Option<Vec3> {
+    targets
+        .iter()
+        .find(|(_, target)| target.0 == index)
+        .map(|(transform, _)| transform.translation)
+}
+
+pub(super) fn follow_target(
+    targets: Query<(&Transform, &CameraTarget)>,
+    mut camera_manager: ResMut<CameraManager>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+
+    for camera in camera_manager.cameras.values_mut() {
+        let Some(follow) = camera.follow else {
+            continue;
+        };
+        let Some(position) = target_position(&targets, camera.target) else {
+            continue;
+        };
+        let Some(last_target) = camera.follow_state.last_target.replace(position) else {
+            camera.pivot = position;
+            continue;
+        };
+        if dt <= 0. {
+            continue;
+        }
+
+        let mut velocity = (position - last_target) / dt;
+        velocity.y = 0.;
+        let goal = position + velocity * follow.look_ahead;
+
+        // dead zone is aligned with the view, measured in the yaw frame of the camera
+        let yaw = Quat::from_rotation_y(camera.angle_yaw.to_radians());
+        let local = yaw.inverse() * (goal - camera.pivot);
+        let outside = Vec2::new(local.x, local.z)
+            - Vec2::new(local.x, local.z).clamp(-follow.dead_zone, follow.dead_zone);
+        let goal = camera.pivot + yaw * Vec3::new(outside.x, 0., outside.y) + Vec3::Y * local.y;
+
+        camera.pivot = smooth_damp(
+            camera.pivot,
+            goal,
+            &mut camera.follow_state.velocity,
+            follow.smooth_time,
+            dt,
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use avian3d::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
+
+use super::{
+    follow::{self, CameraTarget},
+    viewport::IsometricView,
+    CameraManager,
+};
+use crate::game_world::{Tree, Wall};
+
+const FADED_ALPHA: f32 = 0.25;
+// alpha per second
+const FADE_SPEED: f32 = 4.;
+// sight line is a sphere sweep, so the whole target stays visible and not just its origin
+const SIGHT_RADIUS: f32 = 0.5;
+// aim at the body of the target instead of its feet
+const TARGET_HEIGHT: f32 = 1.;
+const MAX_OCCLUDERS: u32 = 16;
+
+/**
+ * Mesh of a Wall or Tree that is, or was recently, between a rendered camera and its target.
+ * It renders with its own copy of the material while faded, the original is put back after
+ */
+#[derive(Component)]
+pub struct OcclusionFade {
+    alpha: f32,
+    original: Handle<StandardMaterial>,
+}
+
+pub(super) fn fade_occluders(
+    spatial_query: SpatialQuery,
+    camera_manager: Res<CameraManager>,
+    views: Query<&IsometricView>,
+    targets: Query<(&Transform, &CameraTarget)>,
+    occluders: Query<(), Or<(With<Wall>, With<Tree>)>>,
+    children: Query<&Children>,
+    mut meshes: Query<(
+        Entity,
+        &mut MeshMaterial3d<StandardMaterial>,
+        Option<&mut OcclusionFade>,
+    )>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    // meshes of occluders, an occluder from a scene has its meshes in children
+    let mut occluding = HashSet::<Entity>::new();
+    for camera in views
+        .iter()
+        .filter_map(|view| camera_manager.get_view(view))
+    {
+        if !camera.fade_occluders {
+            continue;
+        }
+        let Some(target) = follow::target_position(&targets, camera.target) else {
+            continue;
+        };
+        let from = camera.get_camera_transform().translation;
+        let to = target + Vec3::Y * TARGET_HEIGHT;
+        let Ok(direction) = Dir3::new(to - from) else {
+            continue;
+        };
+        let hits = spatial_query.shape_hits(
+            &Collider::sphere(SIGHT_RADIUS),
+            from,
+            Quat::IDENTITY,
+            direction,
+            MAX_OCCLUDERS,
+            &ShapeCastConfig::from_max_distance(from.distance(to)),
+            &SpatialQueryFilter::default(),
+        );
+        for hit in hits.iter().filter(|hit| occluders.contains(hit.entity)) {
+            occluding.insert(hit.entity);
+            occluding.extend(children.iter_descendants(hit.entity));
+        }
+    }
+
+    let step = FADE_SPEED * time.delta_secs();
+    for (entity, mut material, fade) in meshes.iter_mut() {
+        let is_occluding = occluding.contains(&entity);
+        match fade {
+            None if is_occluding => {
+                let Some(mut faded) = materials.get(&material.0).cloned() else {
+                    continue;
+                };
+                faded.alpha_mode = AlphaMode::Blend;
+                commands.entity(entity).insert(OcclusionFade {
+                    alpha: 1.,
+                    original: std::mem::replace(&mut material.0, materials.add(faded)),
+                });
+            }
+            None => (),
+            Some(mut fade) => {
+                let target_alpha = if is_occluding { FADED_ALPHA } else { 1. };
+                fade.alpha = if target_alpha < fade.alpha {
+                    (fade.alpha - step).max(target_alpha)
+                } else {
+                    (fade.alpha + step).min(target_alpha)
+                };
+                if !is_occluding && fade.alpha >= 1. {
+                    material.0 = fade.original.clone();
+                    commands.entity(entity).remove::<OcclusionFade>();
+                } else if let Some(faded) = materials.get_mut(&material.0) {
+                    faded.base_color.set_alpha(fade.alpha);
+                }
+            }
+        }
+    }
+}
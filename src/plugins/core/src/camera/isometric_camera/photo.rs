@@ -0,0 +1,384 @@
+use bevy::{
+    core_pipeline::dof::DepthOfField,
+    prelude::*,
+    render::{
+        camera::Exposure,
+        view::window::screenshot::{save_to_disk, Screenshot},
+    },
+};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{viewport::IsometricView, CameraManager, CameraMode, IsometricCamera};
+use crate::input::input_manager::{
+    button, contexts::InputContext, motion, Action, InputManager, InputType,
+};
+
+static ENTER_PHOTO: Action = Action("enter_photo_mode");
+static EXIT_PHOTO: Action = Action("exit_photo_mode");
+static TAKE_PHOTO: Action = Action("take_photo");
+static TOGGLE_DEPTH_OF_FIELD: Action = Action("photo_toggle_depth_of_field");
+// y is focal distance, x is aperture
+static PHOTO_FOCUS: Action = Action("photo_focus");
+static PHOTO_EXPOSURE: Action = Action("photo_exposure");
+static PHOTO_FOV: Action = Action("photo_fov");
+
+const SCREENSHOT_DIR: &str = "sporomancer/screenshots";
+// degrees per second
+const FOV_SPEED: f32 = 30.;
+// world units per second
+const FOCUS_SPEED: f32 = 5.;
+const MIN_FOCAL_DISTANCE: f32 = 0.1;
+// f-stops per second, the aperture doubles or halves with each
+const APERTURE_SPEED: f32 = 1.;
+const MIN_APERTURE: f32 = 0.5;
+const MAX_APERTURE: f32 = 32.;
+// ev100 per second
+const EXPOSURE_SPEED: f32 = 2.;
+
+/// Lens of the photo camera, adjusted while in photo mode and kept for the next time
+#[derive(Debug, Clone, Copy)]
+pub struct PhotoSettings {
+    pub depth_of_field: bool,
+    // world units from the camera that are in focus
+    pub focal_distance: f32,
+    // lower is a shallower depth of field
+    pub aperture_f_stops: f32,
+    pub exposure_ev100: f32,
+}
+
+impl Default for PhotoSettings {
+    fn default() -> Self {
+        Self {
+            depth_of_field: true,
+            focal_distance: 10.,
+            aperture_f_stops: 1.,
+            exposure_ev100: Exposure::EV100_BLENDER,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct PhotoMode {
+    settings: PhotoSettings,
+    session: Option<PhotoSession>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PhotoSession {
+    previous_mode: CameraMode,
+    // the simulation is only resumed on exit if photo mode paused it
+    was_paused: bool,
+}
+
+/// UI root hidden by photo mode, with the visibility it had
+#[derive(Component)]
+pub struct PhotoHidden(Visibility);
+
+/// Camera entity changed by photo mode, with what to restore on exit
+#[derive(Component)]
+pub struct PhotoEffects {
+    exposure: Option<Exposure>,
+    is_active: bool,
+}
+
+impl CameraManager {
+    pub fn is_photo_mode(&self) -> bool {
+        self.photo.session.is_some()
+    }
+
+    pub fn get_photo_settings(&self) -> PhotoSettings {
+        self.photo.settings
+    }
+
+    pub fn set_photo_settings(&mut self, settings: PhotoSettings) {
+        self.photo.settings = settings;
+    }
+}
+
+pub fn screenshot_dir() -> Option<PathBuf> {
+    dirs::picture_dir()
+        .or_else(dirs::data_dir)
+        .map(|dir| dir.join(SCREENSHOT_DIR))
+}
+
+// UTC date and time for file names, e.g. 2026-10-18_14-03-22-123
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs();
+    let (days, time) = ((secs / 86400) as i64, secs % 86400);
+
+    // days since 1970-01-01 to a civil date, proleptic gregorian
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}-{:03}",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        now.subsec_millis()
+    )
+}
+
+pub(super) fn register_input(mut im: ResMut<InputManager>) {
+    im.register_action_button(
+        ENTER_PHOTO,
+        vec![
+            button::Variant::Keyboard(KeyCode::KeyP),
+            button::Variant::Gamepad(GamepadButton::DPadUp),
+        ],
+    );
+
+    for action in [
+        EXIT_PHOTO,
+        TAKE_PHOTO,
+        TOGGLE_DEPTH_OF_FIELD,
+        PHOTO_FOCUS,
+        PHOTO_EXPOSURE,
+        PHOTO_FOV,
+    ] {
+        im.set_action_context(action, InputContext::PHOTO);
+    }
+    im.register_action_button(
+        EXIT_PHOTO,
+        vec![
+            button::Variant::Keyboard(KeyCode::KeyP),
+            button::Variant::Gamepad(GamepadButton::DPadUp),
+        ],
+    );
+    im.register_action_button(
+        TAKE_PHOTO,
+        vec![
+            button::Variant::Keyboard(KeyCode::Space),
+            button::Variant::Gamepad(GamepadButton::South),
+        ],
+    );
+    im.register_action_button(
+        TOGGLE_DEPTH_OF_FIELD,
+        vec![
+            button::Variant::Keyboard(KeyCode::KeyT),
+            button::Variant::Gamepad(GamepadButton::North),
+        ],
+    );
+    im.register_action_motion(
+        PHOTO_FOCUS,
+        vec![motion::Entry {
+            input_type: InputType::Keyboard,
+            relations: vec![
+                motion::Relation::KeyCode(KeyCode::ArrowUp, motion::Axis::PosY),
+                motion::Relation::KeyCode(KeyCode::ArrowDown, motion::Axis::NegY),
+                motion::Relation::KeyCode(KeyCode::ArrowRight, motion::Axis::PosX),
+                motion::Relation::KeyCode(KeyCode::ArrowLeft, motion::Axis::NegX),
+            ],
+        }],
+    );
+    im.register_action_motion(
+        PHOTO_EXPOSURE,
+        vec![motion::Entry {
+            input_type: InputType::Keyboard,
+            relations: vec![
+                motion::Relation::KeyCode(KeyCode::KeyR, motion::Axis::PosY),
+                motion::Relation::KeyCode(KeyCode::KeyF, motion::Axis::NegY),
+            ],
+        }],
+    );
+    im.register_action_motion(
+        PHOTO_FOV,
+        vec![motion::Entry {
+            input_type: InputType::Keyboard,
+            relations: vec![
+                motion::Relation::KeyCode(KeyCode::KeyX, motion::Axis::PosY),
+                motion::Relation::KeyCode(KeyCode::KeyZ, motion::Axis::NegY),
+            ],
+        }],
+    );
+}
+
+/**
+ * Enters photo mode from gameplay and leaves it back to the camera from before.
+ * The simulation is paused and the photo camera flies with the editor controls,
+ * so the editor context goes under the photo context
+ */
+pub(super) fn toggle_photo_mode(
+    mut im: ResMut<InputManager>,
+    mut camera_manager: ResMut<CameraManager>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if !camera_manager.is_photo_mode() && im.is_action_just_pressed(ENTER_PHOTO) {
+        // start where the current camera is looking
+        let current = camera_manager.get();
+        let view = current.view();
+        let photo = IsometricCamera {
+            pivot: current.view_pivot(),
+            angle_yaw: view.yaw,
+            angle_pitch: view.pitch,
+            spring_arm_length: current.arm_length,
+            arm_length: current.arm_length,
+            arm_collision: false,
+            zones: false,
+            fade_occluders: false,
+            projection: current.projection,
+            fov: current.fov,
+            ..default()
+        };
+        camera_manager.cameras.insert(CameraMode::Photo, photo);
+        camera_manager.photo.session = Some(PhotoSession {
+            previous_mode: camera_manager.current_mode,
+            was_paused: time.is_paused(),
+        });
+        camera_manager.set_mode(CameraMode::Photo);
+
+        time.pause();
+        im.push_context(InputContext::EDITOR, true);
+        im.push_context(InputContext::PHOTO, false);
+    } else if im.is_action_just_pressed(EXIT_PHOTO) {
+        let Some(session) = camera_manager.photo.session.take() else {
+            return;
+        };
+        camera_manager.set_mode(session.previous_mode);
+
+        if !session.was_paused {
+            time.unpause();
+        }
+        im.remove_context(InputContext::PHOTO);
+        im.remove_context(InputContext::EDITOR);
+    }
+}
+
+/// Hides every UI root while in photo mode, also the ones spawned after entering it
+pub(super) fn hide_ui(
+    camera_manager: Res<CameraManager>,
+    shown: Query<(Entity, &Visibility), (With<Node>, Without<Parent>, Without<PhotoHidden>)>,
+    hidden: Query<(Entity, &PhotoHidden)>,
+    mut commands: Commands,
+) {
+    if camera_manager.is_photo_mode() {
+        for (entity, visibility) in shown.iter() {
+            commands
+                .entity(entity)
+                .insert((PhotoHidden(*visibility), Visibility::Hidden));
+        }
+    } else {
+        for (entity, hidden) in hidden.iter() {
+            commands
+                .entity(entity)
+                .insert(hidden.0)
+                .remove::<PhotoHidden>();
+        }
+    }
+}
+
+// the simulation is paused, so everything here runs on real time
+pub(super) fn process_input(
+    im: Res<InputManager>,
+    mut camera_manager: ResMut<CameraManager>,
+    time: Res<Time<Real>>,
+    mut commands: Commands,
+) {
+    if !camera_manager.is_photo_mode() {
+        return;
+    }
+    let dt = time.delta_secs();
+
+    let fov = im.get_motion(PHOTO_FOV).raw_vec2().y;
+    if fov != 0. {
+        let current = camera_manager.get_fov(CameraMode::Photo);
+        camera_manager.set_fov(CameraMode::Photo, current + fov * FOV_SPEED * dt);
+    }
+
+    let settings = &mut camera_manager.photo.settings;
+    if im.is_action_just_pressed(TOGGLE_DEPTH_OF_FIELD) {
+        settings.depth_of_field = !settings.depth_of_field;
+    }
+    let focus = im.get_motion(PHOTO_FOCUS).raw_vec2();
+    settings.focal_distance =
+        (settings.focal_distance + focus.y * FOCUS_SPEED * dt).max(MIN_FOCAL_DISTANCE);
+    settings.aperture_f_stops = (settings.aperture_f_stops
+        * 2_f32.powf(focus.x * APERTURE_SPEED * dt))
+    .clamp(MIN_APERTURE, MAX_APERTURE);
+    settings.exposure_ev100 += im.get_motion(PHOTO_EXPOSURE).raw_vec2().y * EXPOSURE_SPEED * dt;
+
+    if im.is_action_just_pressed(TAKE_PHOTO) {
+        let Some(dir) = screenshot_dir() else {
+            error!("no directory to save screenshots in");
+            return;
+        };
+        if let Err(err) = fs::create_dir_all(&dir) {
+            error!("could not create screenshot directory: {err}");
+            return;
+        }
+        let path = dir.join(format!("sporomancer_{}.png", timestamp()));
+        commands
+            .spawn(Screenshot::primary_window())
+            .observe(save_to_disk(path));
+    }
+}
+
+/**
+ * Puts the lens settings on the views showing the photo camera,
+ * other views like a minimap are turned off so they are not in the photo
+ */
+pub(super) fn apply_photo_effects(
+    camera_manager: Res<CameraManager>,
+    mut views: Query<(
+        Entity,
+        &IsometricView,
+        &mut Camera,
+        Option<&Exposure>,
+        Option<&PhotoEffects>,
+    )>,
+    mut commands: Commands,
+) {
+    let settings = camera_manager.photo.settings;
+    for (entity, view, mut camera, exposure, effects) in views.iter_mut() {
+        let mut entity = commands.entity(entity);
+        if !camera_manager.is_photo_mode() {
+            let Some(effects) = effects else {
+                continue;
+            };
+            match effects.exposure {
+                Some(exposure) => entity.insert(exposure),
+                None => entity.remove::<Exposure>(),
+            };
+            entity.remove::<(DepthOfField, PhotoEffects)>();
+            camera.is_active = effects.is_active;
+            continue;
+        }
+
+        if effects.is_none() {
+            entity.insert(PhotoEffects {
+                exposure: exposure.copied(),
+                is_active: camera.is_active,
+            });
+        }
+        if camera_manager.view_mode(view) != CameraMode::Photo {
+            camera.is_active = false;
+            continue;
+        }
+        entity.insert(Exposure {
+            ev100: settings.exposure_ev100,
+        });
+        if settings.depth_of_field {
+            entity.insert(DepthOfField {
+                focal_distance: settings.focal_distance,
+                aperture_f_stops: settings.aperture_f_stops,
+                ..default()
+            });
+        } else {
+            entity.remove::<DepthOfField>();
+        }
+    }
+}
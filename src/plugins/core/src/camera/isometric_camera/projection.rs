@@ -0,0 +1,102 @@
+use bevy::{prelude::*, render::camera::ScalingMode};
+
+use super::{viewport::IsometricView, CameraManager, CameraMode};
+
+const MIN_ZOOM: f32 = 1.;
+// degrees
+const MIN_FOV: f32 = 5.;
+const MAX_FOV: f32 = 150.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IsometricProjection {
+    Perspective,
+    // height of the view in world units, independent of the spring arm
+    Orthographic { visible_height: f32 },
+}
+
+impl CameraManager {
+    pub fn set_projection(&mut self, mode: CameraMode, projection: IsometricProjection) {
+        self.cameras.get_mut(&mode).unwrap().projection = projection;
+    }
+
+    pub fn get_projection(&self, mode: CameraMode) -> IsometricProjection {
+        self.cameras.get(&mode).unwrap().projection
+    }
+
+    /// Vertical field of view in degrees, only used by perspective projections
+    pub fn set_fov(&mut self, mode: CameraMode, fov: f32) {
+        self.cameras.get_mut(&mode).unwrap().fov = fov.clamp(MIN_FOV, MAX_FOV);
+    }
+
+    pub fn get_fov(&self, mode: CameraMode) -> f32 {
+        self.cameras.get(&mode).unwrap().fov
+    }
+
+    /**
+     * Zooms the current camera out by world units, negative zooms in.
+     * Orthographic cameras show more of the world, perspective cameras extend the spring arm
+     */
+    pub fn zoom_camera(&mut self, units: f32) {
+        let camera = self.get_mut();
+        match &mut camera.projection {
+            IsometricProjection::Orthographic { visible_height } => {
+                *visible_height = (*visible_height + units).max(MIN_ZOOM);
+            }
+            IsometricProjection::Perspective => {
+                camera.spring_arm_length = (camera.spring_arm_length + units).max(MIN_ZOOM);
+            }
+        }
+    }
+}
+
+// only writes the projection on change, so the camera frustum is not recomputed every frame
+pub(super) fn update_projection(
+    camera_manager: Res<CameraManager>,
+    mut views: Query<(&IsometricView, &mut Projection)>,
+) {
+    for (view, mut projection) in views.iter_mut() {
+        let Some(camera) = camera_manager.get_view(view) else {
+            continue;
+        };
+        match (&*projection, camera.projection) {
+            (Projection::Perspective(current), IsometricProjection::Perspective) => {
+                let fov = camera.fov.to_radians();
+                if current.fov != fov {
+                    if let Projection::Perspective(current) = &mut *projection {
+                        current.fov = fov;
+                    }
+                }
+            }
+            (Projection::Perspective(_), IsometricProjection::Orthographic { visible_height }) => {
+                *projection = Projection::Orthographic(OrthographicProjection {
+                    scaling_mode: ScalingMode::FixedVertical {
+                        viewport_height: visible_height,
+                    },
+                    ..OrthographicProjection::default_3d()
+                });
+            }
+            (Projection::Orthographic(_), IsometricProjection::Perspective) => {
+                *projection = Projection::Perspective(PerspectiveProjection {
+                    fov: camera.fov.to_radians(),
+                    ..default()
+                });
+            }
+            (
+                Projection::Orthographic(current),
+                IsometricProjection::Orthographic { visible_height },
+            ) => {
+                let is_current = matches!(
+                    current.scaling_mode,
+                    ScalingMode::FixedVertical { viewport_height } if viewport_height == visible_height
+                );
+                if !is_current {
+                    if let Projection::Orthographic(current) = &mut *projection {
+                        current.scaling_mode = ScalingMode::FixedVertical {
+                            viewport_height: visible_height,
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
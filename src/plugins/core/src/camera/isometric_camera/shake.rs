@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+
+use super::{CameraManager, CameraMode, IsometricCamera};
+use crate::utils::noise::value_noise;
+
+/// Adds trauma to the current camera, total trauma is clamped to 1
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CameraShake {
+    pub trauma: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShakeSettings {
+    // world units the camera moves at full trauma
+    pub max_offset: f32,
+    // degrees the camera rolls at full trauma
+    pub max_roll: f32,
+    // noise samples per second, higher is more jittery
+    pub frequency: f32,
+    // trauma lost per second
+    pub decay: f32,
+    // shake is trauma to this power, so small hits stay subtle
+    pub exponent: f32,
+}
+
+impl Default for ShakeSettings {
+    fn default() -> Self {
+        Self {
+            max_offset: 0.4,
+            max_roll: 3.,
+            frequency: 18.,
+            decay: 1.5,
+            exponent: 2.,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ShakeState {
+    trauma: f32,
+    time: f32,
+    // camera right and up
+    offset: Vec2,
+    // degrees
+    roll: f32,
+}
+
+impl CameraManager {
+    pub fn set_shake_settings(&mut self, mode: CameraMode, settings: ShakeSettings) {
+        self.cameras.get_mut(&mode).unwrap().shake_settings = settings;
+    }
+
+    pub fn get_trauma(&self) -> f32 {
+        self.get().shake.trauma
+    }
+}
+
+impl IsometricCamera {
+    /**
+     * Shake only moves the rendered transform, it is applied where the camera entity is written.
+     * get_camera_transform stays unshaken so follow, movement directions, panning
+     * and occlusion rays don't shake along
+     */
+    pub(super) fn apply_shake(&self, mut transform: Transform) -> Transform {
+        if self.shake.trauma <= 0. {
+            return transform;
+        }
+        transform.translation +=
+            transform.right() * self.shake.offset.x + transform.up() * self.shake.offset.y;
+        transform.rotate_local_z(self.shake.roll.to_radians());
+        transform
+    }
+}
+
+pub(super) fn add_trauma(trigger: Trigger<CameraShake>, mut camera_manager: ResMut<CameraManager>) {
+    let shake = &mut camera_manager.get_mut().shake;
+    shake.trauma = (shake.trauma + trigger.event().trauma).clamp(0., 1.);
+}
+
+pub(super) fn update_shake(mut camera_manager: ResMut<CameraManager>, time: Res<Time>) {
+    for camera in camera_manager.cameras.values_mut() {
+        let settings = camera.shake_settings;
+        let shake = &mut camera.shake;
+        if shake.trauma <= 0. {
+            continue;
+        }
+        shake.time += time.delta_secs();
+        shake.trauma = (shake.trauma - settings.decay * time.delta_secs()).max(0.);
+
+        let amount = shake.trauma.powf(settings.exponent);
+        let t = shake.time * settings.frequency;
+        shake.offset =
+            Vec2::new(value_noise(t, 0), value_noise(t, 1)) * settings.max_offset * amount;
+        shake.roll = value_noise(t, 2) * settings.max_roll * amount;
+    }
+}
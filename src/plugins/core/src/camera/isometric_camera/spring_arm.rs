@@ -0,0 +1,59 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use super::CameraManager;
+use crate::game_world::Tree;
+
+// radius of the sphere swept along the arm, keeps the near plane out of walls
+const ARM_PROBE_RADIUS: f32 = 0.4;
+// units per second the arm grows back once unblocked, shrinking is instant
+const ARM_RETURN_SPEED: f32 = 10.;
+const MIN_ARM_LENGTH: f32 = 1.;
+
+/**
+ * Shortens the arm of each camera when static geometry is between pivot and camera,
+ * for cameras with arm_collision, the editor camera flies through everything
+ * Trees are thin and get faded instead, see occlusion
+ */
+pub(super) fn update_spring_arm(
+    spatial_query: SpatialQuery,
+    bodies: Query<&RigidBody, Without<Tree>>,
+    mut camera_manager: ResMut<CameraManager>,
+    time: Res<Time>,
+) {
+    for camera in camera_manager.cameras.values_mut() {
+        let desired_length = camera.view().arm_length;
+        if !camera.arm_collision {
+            camera.arm_length = desired_length;
+            continue;
+        }
+        let Ok(direction) = Dir3::new(camera.arm_direction()) else {
+            continue;
+        };
+
+        let config = ShapeCastConfig {
+            max_distance: desired_length,
+            // a pivot inside a collider, e.g. the player against a wall, should not collapse the arm
+            ignore_origin_penetration: true,
+            ..default()
+        };
+        let target_length = spatial_query
+            .cast_shape_predicate(
+                &Collider::sphere(ARM_PROBE_RADIUS),
+                camera.view_pivot(),
+                Quat::IDENTITY,
+                direction,
+                &config,
+                &SpatialQueryFilter::default(),
+                &|entity| bodies.get(entity).is_ok_and(|body| body.is_static()),
+            )
+            .map(|hit| hit.distance.max(MIN_ARM_LENGTH))
+            .unwrap_or(desired_length);
+
+        camera.arm_length = if target_length < camera.arm_length {
+            target_length
+        } else {
+            (camera.arm_length + ARM_RETURN_SPEED * time.delta_secs()).min(target_length)
+        };
+    }
+}
@@ -0,0 +1,89 @@
+use bevy::{prelude::*, render::camera::Viewport, window::PrimaryWindow};
+
+use super::{follow::CameraFollow, yaw_snap::YawSnap, CameraManager, CameraMode, IsometricCamera};
+
+/**
+ * Camera entity rendering one IsometricCamera of the CameraManager.
+ * Several views render at once for split-screen or a minimap, give each its own Camera order
+ */
+#[derive(Component, Debug, Clone, Copy, Default)]
+#[require(Camera3d)]
+pub struct IsometricView {
+    // None shows the current mode, so it switches to the editor camera with set_mode
+    pub mode: Option<CameraMode>,
+    // part of the window, 0..1 from the top left, None fills the window
+    pub viewport: Option<Rect>,
+}
+
+impl IsometricView {
+    pub fn new(mode: CameraMode) -> Self {
+        Self {
+            mode: Some(mode),
+            viewport: None,
+        }
+    }
+
+    pub fn with_viewport(mut self, viewport: Rect) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
+}
+
+impl CameraManager {
+    /**
+     * Adds a game camera for split-screen, it follows the CameraTarget with the same index.
+     * Index 0 is the Game camera
+     */
+    pub fn add_split_camera(&mut self, index: u8) -> CameraMode {
+        let mode = CameraMode::Split(index);
+        self.cameras.insert(
+            mode,
+            IsometricCamera {
+                target: index,
+                follow: Some(CameraFollow::default()),
+                yaw_snap: Some(YawSnap::default()),
+                ..default()
+            },
+        );
+        mode
+    }
+
+    pub(super) fn view_mode(&self, view: &IsometricView) -> CameraMode {
+        view.mode.unwrap_or(self.current_mode)
+    }
+
+    pub(super) fn get_view(&self, view: &IsometricView) -> Option<&IsometricCamera> {
+        self.cameras.get(&self.view_mode(view))
+    }
+}
+
+// window size is read every frame, so viewports follow resizing
+pub(super) fn update_viewports(
+    window: Option<Single<&Window, With<PrimaryWindow>>>,
+    mut views: Query<(&IsometricView, &mut Camera)>,
+) {
+    let Some(window) = window else {
+        return;
+    };
+    let size = window.physical_size().as_vec2();
+    for (view, mut camera) in views.iter_mut() {
+        let rect = view.viewport.map(|rect| {
+            (
+                (rect.min * size).as_uvec2(),
+                (rect.size() * size).as_uvec2().max(UVec2::ONE),
+            )
+        });
+        let current = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size));
+        if rect == current {
+            continue;
+        }
+        camera.viewport = rect.map(|(physical_position, physical_size)| Viewport {
+            physical_position,
+            physical_size,
+            ..default()
+        });
+    }
+}
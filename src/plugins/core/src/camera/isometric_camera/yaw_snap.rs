@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+
+use super::{CameraManager, IsometricCamera};
+use crate::input::input_manager::{button, Action, InputManager};
+
+static ROTATE_LEFT: Action = Action("camera_rotate_left");
+static ROTATE_RIGHT: Action = Action("camera_rotate_right");
+
+/// Camera yaw moves in fixed steps, each step eased in over duration
+#[derive(Debug, Clone, Copy)]
+pub struct YawSnap {
+    // degrees
+    pub step: f32,
+    // seconds
+    pub duration: f32,
+}
+
+impl Default for YawSnap {
+    fn default() -> Self {
+        Self {
+            step: 90.,
+            duration: 0.35,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct YawRotation {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+}
+
+impl CameraManager {
+    /// Rotates the current camera by whole steps of its YawSnap, presses during a rotation add up
+    pub fn snap_camera_yaw(&mut self, steps: i32) {
+        self.get_mut().snap_yaw(steps)
+    }
+
+    /// None gives the camera free rotation through rotate_camera_yaw
+    pub fn set_yaw_snap(&mut self, mode: super::CameraMode, snap: Option<YawSnap>) {
+        let camera = self.cameras.get_mut(&mode).unwrap();
+        if let Some(rotation) = camera.yaw_rotation.take() {
+            camera.angle_yaw = rotation.to;
+        }
+        camera.yaw_snap = snap;
+    }
+
+    /// Yaw in degrees the current camera is at or rotating towards
+    pub fn get_settled_yaw(&self) -> f32 {
+        self.get().settled_yaw()
+    }
+}
+
+impl IsometricCamera {
+    fn snap_yaw(&mut self, steps: i32) {
+        let Some(snap) = self.yaw_snap else {
+            return;
+        };
+        self.yaw_rotation = Some(YawRotation {
+            from: self.angle_yaw,
+            to: self.snapped_yaw() + steps as f32 * snap.step,
+            elapsed: 0.,
+        });
+    }
+
+    fn snapped_yaw(&self) -> f32 {
+        self.yaw_rotation
+            .map(|rotation| rotation.to)
+            .unwrap_or(self.angle_yaw)
+    }
+
+    // a camera zone with its own yaw decides where the camera settles
+    pub(super) fn settled_yaw(&self) -> f32 {
+        self.zone_yaw().unwrap_or(self.snapped_yaw())
+    }
+}
+
+pub(super) fn register_input(mut im: ResMut<InputManager>) {
+    im.register_action_button(
+        ROTATE_LEFT,
+        vec![
+            button::Variant::Keyboard(KeyCode::Comma),
+            button::Variant::Gamepad(GamepadButton::LeftTrigger),
+        ],
+    );
+    im.register_action_button(
+        ROTATE_RIGHT,
+        vec![
+            button::Variant::Keyboard(KeyCode::Period),
+            button::Variant::Gamepad(GamepadButton::RightTrigger),
+        ],
+    );
+}
+
+pub(super) fn process_input(im: Res<InputManager>, mut camera_manager: ResMut<CameraManager>) {
+    if im.is_action_just_pressed(ROTATE_LEFT) {
+        camera_manager.snap_camera_yaw(-1);
+    }
+    if im.is_action_just_pressed(ROTATE_RIGHT) {
+        camera_manager.snap_camera_yaw(1);
+    }
+}
+
+pub(super) fn update_yaw_rotation(mut camera_manager: ResMut<CameraManager>, time: Res<Time>) {
+    for camera in camera_manager.cameras.values_mut() {
+        let (Some(snap), Some(mut rotation)) = (camera.yaw_snap, camera.yaw_rotation) else {
+            continue;
+        };
+        rotation.elapsed += time.delta_secs();
+        let t = (rotation.elapsed / snap.duration.max(f32::EPSILON)).min(1.);
+        camera.angle_yaw = EasingCurve::new(rotation.from, rotation.to, EaseFunction::CubicInOut)
+            .sample_clamped(t);
+        camera.yaw_rotation = (t < 1.).then_some(rotation);
+    }
+}
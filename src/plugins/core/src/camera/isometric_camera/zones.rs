@@ -0,0 +1,228 @@
+use avian3d::prelude::*;
+use bevy::{math::cubic_splines::InsufficientDataError, prelude::*};
+
+use super::{
+    follow::{self, CameraTarget},
+    CameraManager, CameraMode, IsometricCamera,
+};
+
+// rail samples per spline segment, the pivot is projected onto the resulting polyline
+const RAIL_SUBDIVISIONS: usize = 16;
+
+/**
+ * Sensor volume that blends game cameras to its view while their CameraTarget is inside.
+ * Zones stack, the one entered last wins until the target leaves it.
+ * Unset angles and arm length keep what the camera has outside the zone
+ */
+#[derive(Component, Debug, Clone)]
+#[require(Sensor)]
+pub struct CameraZone {
+    // degrees
+    pub yaw: Option<f32>,
+    pub pitch: Option<f32>,
+    pub arm_length: Option<f32>,
+    pub pivot_offset: Vec3,
+    // entity with a CameraRail the pivot is pulled onto, e.g. for corridors
+    pub rail: Option<Entity>,
+    // seconds to blend in when entered, and back out when left
+    pub blend_time: f32,
+}
+
+impl Default for CameraZone {
+    fn default() -> Self {
+        Self {
+            yaw: None,
+            pitch: None,
+            arm_length: None,
+            pivot_offset: Vec3::ZERO,
+            rail: None,
+            blend_time: 0.75,
+        }
+    }
+}
+
+/// Catmull-Rom spline through the control points, in world space
+#[derive(Component, Debug, Clone)]
+pub struct CameraRail {
+    points: Vec<Vec3>,
+}
+
+impl CameraRail {
+    pub fn new(control_points: Vec<Vec3>) -> Result<Self, InsufficientDataError> {
+        let curve = CubicCardinalSpline::new_catmull_rom(control_points).to_curve()?;
+        let subdivisions = curve.segments().len() * RAIL_SUBDIVISIONS;
+        Ok(Self {
+            points: curve.iter_positions(subdivisions).collect(),
+        })
+    }
+
+    pub fn nearest_point(&self, point: Vec3) -> Vec3 {
+        if self.points.len() == 1 {
+            return self.points[0];
+        }
+        self.points
+            .windows(2)
+            .map(|segment| {
+                let (a, b) = (segment[0], segment[1]);
+                let t = ((point - a).dot(b - a) / (b - a).length_squared().max(f32::EPSILON))
+                    .clamp(0., 1.);
+                a.lerp(b, t)
+            })
+            .min_by(|a, b| {
+                a.distance_squared(point)
+                    .total_cmp(&b.distance_squared(point))
+            })
+            .unwrap_or(point)
+    }
+}
+
+/// Angles and arm length the camera is rendered with, blended between zones
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct CameraView {
+    pub(super) yaw: f32,
+    pub(super) pitch: f32,
+    pub(super) arm_length: f32,
+    pub(super) pivot_offset: Vec3,
+}
+
+impl CameraView {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        // the short way around, snapped yaws keep counting past 360
+        let yaw_delta = (other.yaw - self.yaw + 180.).rem_euclid(360.) - 180.;
+        Self {
+            yaw: self.yaw + yaw_delta * t,
+            pitch: self.pitch.lerp(other.pitch, t),
+            arm_length: self.arm_length.lerp(other.arm_length, t),
+            pivot_offset: self.pivot_offset.lerp(other.pivot_offset, t),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ZoneState {
+    // zones the target is in, in the order they were entered
+    stack: Vec<Entity>,
+    active: Option<Entity>,
+    zone: Option<CameraZone>,
+    rail: Option<CameraRail>,
+    // view when the last blend started
+    from: CameraView,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl IsometricCamera {
+    fn base_view(&self) -> CameraView {
+        CameraView {
+            yaw: self.angle_yaw,
+            pitch: self.angle_pitch,
+            arm_length: self.spring_arm_length,
+            pivot_offset: Vec3::ZERO,
+        }
+    }
+
+    fn zone_view(&self) -> CameraView {
+        let base = self.base_view();
+        let Some(zone) = &self.zone.zone else {
+            return base;
+        };
+        let rail_offset = self
+            .zone
+            .rail
+            .as_ref()
+            .map(|rail| rail.nearest_point(self.pivot) - self.pivot)
+            .unwrap_or_default();
+        CameraView {
+            yaw: zone.yaw.unwrap_or(base.yaw),
+            pitch: zone.pitch.unwrap_or(base.pitch),
+            arm_length: zone.arm_length.unwrap_or(base.arm_length),
+            pivot_offset: zone.pivot_offset + rail_offset,
+        }
+    }
+
+    /// Current view, blending from the previous zone to the active one
+    pub(super) fn view(&self) -> CameraView {
+        let target = self.zone_view();
+        if self.zone.elapsed >= self.zone.duration {
+            return target;
+        }
+        let t = self.zone.elapsed / self.zone.duration;
+        let eased = EasingCurve::new(0., 1., EaseFunction::CubicInOut).sample_clamped(t);
+        self.zone.from.lerp(&target, eased)
+    }
+
+    /// Yaw the active zone settles at, None if it keeps the camera yaw
+    pub(super) fn zone_yaw(&self) -> Option<f32> {
+        self.zone.zone.as_ref().and_then(|zone| zone.yaw)
+    }
+
+    fn enter_zone(
+        &mut self,
+        active: Option<Entity>,
+        zone: Option<CameraZone>,
+        rail: Option<CameraRail>,
+    ) {
+        let duration = zone
+            .as_ref()
+            .or(self.zone.zone.as_ref())
+            .map(|zone| zone.blend_time)
+            .unwrap_or_default();
+        self.zone.from = self.view();
+        self.zone.active = active;
+        self.zone.zone = zone;
+        self.zone.rail = rail;
+        self.zone.elapsed = 0.;
+        self.zone.duration = duration;
+    }
+}
+
+impl CameraManager {
+    /// Zone the camera is currently blending to or in
+    pub fn get_active_zone(&self, mode: CameraMode) -> Option<Entity> {
+        self.cameras.get(&mode).unwrap().zone.active
+    }
+}
+
+pub(super) fn update_camera_zones(
+    spatial_query: SpatialQuery,
+    targets: Query<(&Transform, &CameraTarget)>,
+    zones: Query<&CameraZone>,
+    rails: Query<&CameraRail>,
+    mut camera_manager: ResMut<CameraManager>,
+    time: Res<Time>,
+) {
+    for camera in camera_manager.cameras.values_mut() {
+        if !camera.zones {
+            continue;
+        }
+        camera.zone.elapsed += time.delta_secs();
+
+        let mut inside = follow::target_position(&targets, camera.target)
+            .map(|position| {
+                spatial_query.point_intersections(position, &SpatialQueryFilter::default())
+            })
+            .unwrap_or_default();
+        inside.retain(|entity| zones.contains(*entity));
+        // zones entered in the same frame are ordered by entity for a stable winner
+        inside.sort();
+
+        camera.zone.stack.retain(|entity| inside.contains(entity));
+        for entity in inside {
+            if !camera.zone.stack.contains(&entity) {
+                camera.zone.stack.push(entity);
+            }
+        }
+
+        let top = camera.zone.stack.last().copied();
+        if top == camera.zone.active {
+            continue;
+        }
+        let zone = top.and_then(|entity| zones.get(entity).ok()).cloned();
+        let rail = zone
+            .as_ref()
+            .and_then(|zone| zone.rail)
+            .and_then(|rail| rails.get(rail).ok())
+            .cloned();
+        camera.enter_zone(top, zone, rail);
+    }
+}
@@ -3,7 +3,10 @@ use std::{any::Any, time::Duration};
 use avian3d::prelude::*;
 use bevy::prelude::*;
 
-use crate::{game_world::Wall, player::states::cordycept::CordyCeptMovement};
+use crate::{
+    camera::isometric_camera::shake::CameraShake, game_world::Wall,
+    player::states::cordycept::CordyCeptMovement,
+};
 
 #[derive(Component)]
 pub struct Ant;
@@ -144,4 +147,5 @@ pub(super) fn kill_ant(
     };
     spawner.decrement();
     commands.entity(event.entity()).remove_parent().despawn();
+    commands.trigger(CameraShake { trauma: 0.4 });
 }
@@ -25,6 +25,8 @@ fn exit_game(ev: EventReader<ExitGameEvent>, mut ev_exit: EventWriter<AppExit>)
 static EXIT_GAME: input::Action = input::Action("exit_game");
 
 fn register_input(mut im: ResMut<input::InputManager>) {
+    // leaving the game works from every context that doesn't block it
+    im.set_action_context(EXIT_GAME, input::contexts::InputContext::GLOBAL);
     im.register_action_button(
         EXIT_GAME,
         vec![
@@ -1,5 +1,23 @@
+pub mod wind;
+
 use bevy::prelude::*;
 
+pub struct GameWorldPlugin;
+impl Plugin for GameWorldPlugin {
+    fn build(&self, app: &mut App) {
+        // wind arrows are for debugging, turn them on through the GizmoConfigStore
+        app.insert_gizmo_config(
+            wind::WindGizmo,
+            GizmoConfig {
+                enabled: false,
+                ..default()
+            },
+        )
+        .add_systems(FixedUpdate, wind::push_bodies)
+        .add_systems(Update, wind::draw_gizmos);
+    }
+}
+
 #[derive(Component)]
 pub struct Ground;
 
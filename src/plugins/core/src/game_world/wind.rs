@@ -0,0 +1,176 @@
+use avian3d::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
+
+use crate::utils::noise::value_noise;
+
+// how quickly dynamic bodies are dragged up to the wind speed, per second
+const BODY_DRAG: f32 = 2.;
+// debug arrows per axis of a volume, and their length per unit of wind speed
+const GIZMO_SAMPLES: usize = 3;
+const GIZMO_SCALE: f32 = 0.25;
+// world units between uncorrelated gusts of turbulence
+const TURBULENCE_SCALE: f32 = 4.;
+
+/**
+ * Sensor volume with wind blowing through it, e.g. an updraft over a chasm.
+ * Ballooning players drift with it, dynamic bodies inside are pushed by it.
+ * The wind is measured from the volume origin for falloff, direction turns with the volume
+ */
+#[derive(Component, Debug, Clone, Copy)]
+#[require(Sensor, CollidingEntities)]
+pub struct WindVolume {
+    // local to the volume, Dir3::Y blows up the volume's own up axis
+    pub direction: Dir3,
+    // world units per second
+    pub strength: f32,
+    // noise on top of the wind, as a fraction of strength
+    pub turbulence: f32,
+    // gusts per second
+    pub turbulence_frequency: f32,
+    pub falloff: WindFalloff,
+}
+
+impl Default for WindVolume {
+    fn default() -> Self {
+        Self {
+            direction: Dir3::X,
+            strength: 5.,
+            turbulence: 0.2,
+            turbulence_frequency: 0.5,
+            falloff: WindFalloff::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindFalloff {
+    // same strength everywhere in the volume
+    None,
+    // fades out linearly to nothing at radius from the origin
+    Radial { radius: f32 },
+    // fades out linearly along the direction, full at and behind the origin, nothing at length,
+    // e.g. an updraft that weakens with height
+    Directional { length: f32 },
+}
+
+impl WindVolume {
+    /**
+     * Wind at point, transform is the one of the volume and elapsed seconds drive the turbulence.
+     * Does not check that point is inside
+     */
+    pub fn wind(&self, transform: &GlobalTransform, point: Vec3, elapsed: f32) -> Vec3 {
+        let direction = transform.rotation() * self.direction.as_vec3();
+        let offset = point - transform.translation();
+        let falloff = match self.falloff {
+            WindFalloff::None => 1.,
+            WindFalloff::Radial { radius } => 1. - offset.length() / radius.max(f32::EPSILON),
+            WindFalloff::Directional { length } => {
+                1. - offset.dot(direction).max(0.) / length.max(f32::EPSILON)
+            }
+        }
+        .clamp(0., 1.);
+
+        // gusts move through the volume instead of the whole volume gusting at once
+        let t = elapsed * self.turbulence_frequency + offset.length() / TURBULENCE_SCALE;
+        let gust = Vec3::new(value_noise(t, 0), value_noise(t, 1), value_noise(t, 2));
+
+        (direction + gust * self.turbulence) * self.strength * falloff
+    }
+}
+
+/// Wind lookup for systems, e.g. `wind: Wind` and then `wind.at(point)`
+#[derive(SystemParam)]
+pub struct Wind<'w, 's> {
+    spatial_query: SpatialQuery<'w, 's>,
+    volumes: Query<'w, 's, (&'static WindVolume, &'static GlobalTransform)>,
+    time: Res<'w, Time>,
+}
+
+impl Wind<'_, '_> {
+    /// Sum of the wind of every volume containing point, in world units per second
+    pub fn at(&self, point: Vec3) -> Vec3 {
+        let elapsed = self.time.elapsed_secs();
+        self.spatial_query
+            .point_intersections(point, &SpatialQueryFilter::default())
+            .into_iter()
+            .filter_map(|entity| self.volumes.get(entity).ok())
+            .map(|(volume, transform)| volume.wind(transform, point, elapsed))
+            .sum()
+    }
+}
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct WindGizmo;
+
+/**
+ * Drags dynamic bodies inside wind volumes towards the wind velocity.
+ * The force does not depend on mass, so light props are blown around and heavy ones barely move
+ */
+pub(super) fn push_bodies(
+    wind: Wind,
+    volumes: Query<&CollidingEntities, With<WindVolume>>,
+    colliders: Query<&ColliderParent>,
+    mut bodies: Query<(
+        Entity,
+        &RigidBody,
+        &GlobalTransform,
+        &LinearVelocity,
+        Option<&mut ExternalImpulse>,
+    )>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    // a body in several volumes is pushed once, by the sum of their wind
+    let inside = volumes
+        .iter()
+        .flat_map(|colliding| colliding.iter())
+        .filter_map(|collider| colliders.get(*collider).ok())
+        .map(ColliderParent::get)
+        .collect::<HashSet<_>>();
+
+    let mut bodies = bodies.iter_many_mut(inside);
+    while let Some((entity, body, transform, velocity, impulse)) = bodies.fetch_next() {
+        if !body.is_dynamic() {
+            continue;
+        }
+        let relative = wind.at(transform.translation()) - velocity.0;
+        let push = relative * BODY_DRAG * time.delta_secs();
+        match impulse {
+            Some(mut impulse) => {
+                impulse.apply_impulse(push);
+            }
+            None => {
+                commands.entity(entity).insert(ExternalImpulse::new(push));
+            }
+        }
+    }
+}
+
+pub(super) fn draw_gizmos(
+    wind: Wind,
+    volumes: Query<&ColliderAabb, With<WindVolume>>,
+    config_store: Res<GizmoConfigStore>,
+    mut gizmos: Gizmos<WindGizmo>,
+) {
+    // every arrow is a spatial query, skip them all while the gizmos are off
+    if !config_store.config::<WindGizmo>().0.enabled {
+        return;
+    }
+    for aabb in volumes.iter() {
+        let size = aabb.max - aabb.min;
+        for x in 0..GIZMO_SAMPLES {
+            for y in 0..GIZMO_SAMPLES {
+                for z in 0..GIZMO_SAMPLES {
+                    // cell centers, so no sample sits on the edge of the volume
+                    let cell =
+                        (Vec3::new(x as f32, y as f32, z as f32) + 0.5) / GIZMO_SAMPLES as f32;
+                    let point = aabb.min + size * cell;
+                    let velocity = wind.at(point);
+                    if velocity != Vec3::ZERO {
+                        gizmos.arrow(point, point + velocity * GIZMO_SCALE, Color::WHITE);
+                    }
+                }
+            }
+        }
+    }
+}
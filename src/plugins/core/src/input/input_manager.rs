@@ -1,31 +1,84 @@
 use bevy::input::gamepad::GamepadEvent;
-use bevy::input::mouse::AccumulatedMouseMotion;
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll};
 use bevy::prelude::*;
-use bevy::utils::hashbrown::HashSet;
+use bevy::utils::HashSet;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
+
+pub mod bindings;
+pub mod buffer;
+pub mod contexts;
+pub mod cursor;
+pub mod prompts;
+pub mod replay;
+pub mod slots;
+pub mod touch;
+
+use slots::PlayerSlot;
 
 pub struct InputManagerPlugin;
 impl Plugin for InputManagerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(InputManager::default()).add_systems(
-            PreUpdate,
-            (
-                determine_input_mode,
-                button::read_button_input,
-                motion::read_motion_input,
-            ),
-        );
+        app.insert_resource(InputManager::default())
+            .add_systems(
+                PreUpdate,
+                (
+                    determine_input_mode,
+                    slots::update_player_slots
+                        .before(button::read_button_input)
+                        .before(motion::read_motion_input),
+                    touch::read_touch_controls
+                        .after(bevy::input::InputSystem)
+                        .before(button::read_button_input)
+                        .before(motion::read_motion_input),
+                    button::read_button_input,
+                    motion::read_motion_input,
+                    (
+                        replay::record_input.run_if(resource_exists::<replay::InputRecorder>),
+                        replay::replay_input.run_if(resource_exists::<replay::InputReplay>),
+                    )
+                        .after(button::read_button_input)
+                        .after(motion::read_motion_input)
+                        // the recorded cursor is the one of this frame
+                        .after(cursor::update_cursor_world),
+                    buffer::buffer_presses
+                        .after(button::read_button_input)
+                        .after(replay::replay_input),
+                ),
+            )
+            // pointer hits are ready after picking
+            .add_systems(
+                PreUpdate,
+                cursor::update_cursor_world.after(bevy::picking::PickSet::Last),
+            )
+            .add_systems(
+                Update,
+                (
+                    touch::update_touch_controls,
+                    prompts::update_changed_prompts,
+                ),
+            )
+            .add_systems(
+                Startup,
+                replay::start_replay.run_if(resource_exists::<replay::InputReplay>),
+            )
+            // after every plugin has registered its actions in Startup
+            .add_systems(PostStartup, bindings::load_user_bindings)
+            .add_observer(bindings::save_user_bindings)
+            .add_observer(prompts::update_prompts_on_mode_change);
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Action(pub &'static str);
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum InputType {
     Keyboard,
     Mouse,
     Gamepad,
+    Touch,
 }
 
 impl InputType {
@@ -33,6 +86,7 @@ impl InputType {
         match mode {
             InputMode::MouseAndKeyboard => *self == Self::Keyboard || *self == Self::Mouse,
             InputMode::Gamepad => *self == Self::Gamepad,
+            InputMode::Touch => *self == Self::Touch,
         }
     }
 }
@@ -41,10 +95,10 @@ impl InputType {
 pub enum InputMode {
     MouseAndKeyboard,
     Gamepad, // xbox gamepad assumed
+    Touch,   // on-screen virtual stick and buttons
 }
 
 #[derive(Debug, Clone, Component)]
-#[allow(dead_code)]
 pub struct InputModeChanged(InputMode);
 
 impl Event for InputModeChanged {
@@ -58,6 +112,8 @@ fn determine_input_mode(
     keyboard: Res<ButtonInput<KeyCode>>,
     mouse: Res<ButtonInput<MouseButton>>,
     mouse_motion: Res<AccumulatedMouseMotion>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    touches: Res<Touches>,
     gamepad_events: EventReader<GamepadEvent>,
 
     mut input_manager: ResMut<InputManager>,
@@ -68,11 +124,16 @@ fn determine_input_mode(
     if keyboard.get_pressed().len() != 0
         || mouse.get_pressed().len() != 0
         || mouse_motion.delta != Vec2::ZERO
+        || mouse_scroll.delta != Vec2::ZERO
     {
         input_mode = Some(InputMode::MouseAndKeyboard);
     }
 
-    // gamepad mode takes priority over MnK
+    if touches.any_just_pressed() {
+        input_mode = Some(InputMode::Touch);
+    }
+
+    // gamepad mode takes priority over touch and MnK
     if gamepad_events.len() != 0 {
         input_mode = Some(InputMode::Gamepad);
     }
@@ -87,8 +148,27 @@ fn determine_input_mode(
 #[derive(Resource)]
 pub struct InputManager {
     current_input_mode: InputMode,
-    button_entries: HashMap<Action, button::ActionEntry>,
-    motion_entries: HashMap<Action, motion::ActionEntry>,
+    // every device merged, what single player gameplay reads
+    actions: SlotInput,
+    slots: HashMap<PlayerSlot, SlotInput>,
+    // overrides the queries while an InputReplay runs
+    replay_frame: Option<replay::InputFrame>,
+    context_stack: Vec<contexts::ContextLayer>,
+    action_contexts: HashMap<Action, contexts::InputContext>,
+    cursor_world: Option<Vec3>,
+    cursor_actions: HashSet<Action>,
+    // aim action -> (cursor action, stick motion action)
+    aim_entries: HashMap<Action, (Action, Action)>,
+    // virtual stick id -> stick vector, written by the touch controls
+    touch_sticks: HashMap<u8, Vec2>,
+    // (touch button id, pressed) since the last read_button_input
+    touch_button_events: Vec<(u8, bool)>,
+    // action -> how long a press stays buffered
+    buffer_windows: HashMap<Action, Duration>,
+    // action -> time of the press, expired presses are dropped every frame
+    buffered_presses: HashMap<Action, Duration>,
+    // bumped whenever bindings are registered or changed, prompts refresh on a new one
+    bindings_generation: u32,
 }
 
 impl InputManager {
@@ -105,8 +185,22 @@ impl Default for InputManager {
     fn default() -> Self {
         Self {
             current_input_mode: InputMode::MouseAndKeyboard,
-            button_entries: HashMap::<Action, button::ActionEntry>::new(),
-            motion_entries: HashMap::<Action, motion::ActionEntry>::new(),
+            actions: SlotInput::default(),
+            slots: HashMap::from([
+                (PlayerSlot::KeyboardMouse, SlotInput::default()),
+                (PlayerSlot::Touch, SlotInput::default()),
+            ]),
+            replay_frame: None,
+            context_stack: contexts::default_stack(),
+            action_contexts: HashMap::<Action, contexts::InputContext>::new(),
+            cursor_world: None,
+            cursor_actions: HashSet::<Action>::new(),
+            aim_entries: HashMap::<Action, (Action, Action)>::new(),
+            touch_sticks: HashMap::<u8, Vec2>::new(),
+            touch_button_events: Vec::<(u8, bool)>::new(),
+            buffer_windows: HashMap::<Action, Duration>::new(),
+            buffered_presses: HashMap::<Action, Duration>::new(),
+            bindings_generation: 0,
         }
     }
 }
@@ -117,61 +211,318 @@ impl InputManager {
      * will overwrite previous ones
      */
     pub fn register_action_motion(&mut self, action: Action, entries: Vec<motion::Entry>) {
-        self.motion_entries.insert(
-            action,
-            motion::ActionEntry {
-                motion_entries: entries
-                    .iter()
-                    .map(|e| (e.clone(), false)) // Disgusting clone
-                    .into_iter()
-                    .collect(),
-                motion: motion::Motion::default(),
-            },
-        );
+        for input in self.slots.values_mut() {
+            input
+                .motion_entries
+                .insert(action, motion::ActionEntry::new(entries.clone(), default()));
+        }
+        self.actions
+            .motion_entries
+            .insert(action, motion::ActionEntry::new(entries, default()));
+        self.bindings_generation = self.bindings_generation.wrapping_add(1);
+        self.report_overlaps(action);
     }
 
     pub fn get_motion(&self, action: Action) -> motion::Motion {
-        if let Some(entry) = self.motion_entries.get(&action) {
-            return entry.motion;
+        if let Some(motion) = self.replayed_motion(action) {
+            return motion::Motion(motion);
+        }
+        self.actions.get_motion(action)
+    }
+
+    pub fn get_motion_settings(&self, action: Action) -> Option<motion::MotionSettings> {
+        self.actions
+            .motion_entries
+            .get(&action)
+            .map(|entry| entry.settings)
+    }
+
+    pub fn set_motion_settings(&mut self, action: Action, settings: motion::MotionSettings) {
+        for input in self.all_inputs_mut() {
+            if let Some(entry) = input.motion_entries.get_mut(&action) {
+                entry.settings = settings;
+            }
         }
-        unreachable!("Missing action: {}", action.0)
     }
 
     pub fn register_action_button(&mut self, action: Action, buttons: Vec<button::Variant>) {
-        self.button_entries.insert(
-            action,
-            button::ActionEntry {
-                just_pressed: HashSet::<button::Variant>::new(),
-                pressed: HashSet::<button::Variant>::new(),
-                just_released: HashSet::<button::Variant>::new(),
-                released: buttons.into_iter().collect::<HashSet<_>>(),
-            },
-        );
+        self.register_action_button_trigger(action, buttons, button::TriggerKind::Press);
+    }
+
+    pub fn register_action_button_trigger(
+        &mut self,
+        action: Action,
+        buttons: Vec<button::Variant>,
+        trigger: button::TriggerKind,
+    ) {
+        for input in self.slots.values_mut() {
+            input.button_entries.insert(
+                action,
+                button::ActionEntry::new(buttons.clone(), trigger.clone()),
+            );
+        }
+        self.actions
+            .button_entries
+            .insert(action, button::ActionEntry::new(buttons, trigger));
+        self.bindings_generation = self.bindings_generation.wrapping_add(1);
+        self.report_overlaps(action);
+    }
+
+    pub fn is_action_pressed(&self, action: Action) -> bool {
+        if let Some(button) = self.replayed_button(action) {
+            return button.pressed;
+        }
+        self.actions.is_action_pressed(action)
+    }
+
+    pub fn is_action_just_pressed(&self, action: Action) -> bool {
+        if let Some(button) = self.replayed_button(action) {
+            return button.just_pressed;
+        }
+        self.actions.is_action_just_pressed(action)
+    }
+
+    pub fn is_action_just_released(&self, action: Action) -> bool {
+        if let Some(button) = self.replayed_button(action) {
+            return button.just_released;
+        }
+        self.actions.is_action_just_released(action)
+    }
+
+    pub fn is_action_held_for(&self, action: Action, duration: Duration) -> bool {
+        if let Some(button) = self.replayed_button(action) {
+            return button.held_for.is_some_and(|held| held >= duration);
+        }
+        self.actions.is_action_held_for(action, duration)
+    }
+
+    pub fn is_action_long_pressed(&self, action: Action) -> bool {
+        if let Some(button) = self.replayed_button(action) {
+            return button.long_pressed;
+        }
+        self.actions.is_action_long_pressed(action)
+    }
+
+    pub fn is_action_tapped(&self, action: Action) -> bool {
+        if let Some(button) = self.replayed_button(action) {
+            return button.tapped;
+        }
+        self.actions.is_action_tapped(action)
+    }
+
+    pub fn is_action_double_tapped(&self, action: Action) -> bool {
+        if let Some(button) = self.replayed_button(action) {
+            return button.double_tapped;
+        }
+        self.actions.is_action_double_tapped(action)
+    }
+
+    /// Input from a single keyboard+mouse or gamepad, for local co-op
+    pub fn slot(&self, slot: PlayerSlot) -> Option<&SlotInput> {
+        self.slots.get(&slot)
+    }
+
+    pub fn get_slots(&self) -> impl Iterator<Item = PlayerSlot> + '_ {
+        self.slots.keys().copied()
+    }
+
+    fn all_inputs_mut(&mut self) -> impl Iterator<Item = &mut SlotInput> {
+        std::iter::once(&mut self.actions).chain(self.slots.values_mut())
+    }
+
+    fn update_button_triggers(&mut self, now: Duration) {
+        for input in self.all_inputs_mut() {
+            input.update_button_triggers(now);
+        }
+    }
+
+    // only the topmost active context that binds the button sees the press
+    fn set_button_pressed(&mut self, slot: PlayerSlot, button: button::Variant) {
+        let receivers = self.button_receivers(button);
+        self.actions.set_button_pressed(button, &receivers);
+        if let Some(input) = self.slots.get_mut(&slot) {
+            input.set_button_pressed(button, &receivers);
+        }
+    }
+
+    // the merged input keeps the button down while another slot still holds it
+    fn set_button_released(&mut self, slot: PlayerSlot, button: button::Variant) {
+        if let Some(input) = self.slots.get_mut(&slot) {
+            input.set_button_released(button);
+        }
+        if !self
+            .slots
+            .values()
+            .any(|input| input.held_buttons.contains(&button))
+        {
+            self.actions.set_button_released(button);
+        }
+    }
+
+    fn move_prev_frame(&mut self) {
+        for input in self.all_inputs_mut() {
+            input.move_prev_frame_just_pressed();
+            input.move_prev_frame_just_released();
+        }
+    }
+}
+
+/**
+ * Action state fed by one set of devices.
+ * InputManager keeps one for all devices merged and one per PlayerSlot,
+ * a button is down in the merged one while any slot holds it
+ */
+#[derive(Default)]
+pub struct SlotInput {
+    button_entries: HashMap<Action, button::ActionEntry>,
+    motion_entries: HashMap<Action, motion::ActionEntry>,
+    // raw button state, independent of registered actions, used for chord modifiers
+    held_buttons: HashSet<button::Variant>,
+}
+
+impl SlotInput {
+    // same bindings and triggers, nothing pressed
+    fn bindings_copy(&self) -> Self {
+        Self {
+            button_entries: self
+                .button_entries
+                .iter()
+                .map(|(action, entry)| {
+                    (
+                        *action,
+                        button::ActionEntry::new(
+                            entry.bindings().copied().collect::<Vec<_>>(),
+                            entry.trigger.clone(),
+                        ),
+                    )
+                })
+                .collect(),
+            motion_entries: self
+                .motion_entries
+                .iter()
+                .map(|(action, entry)| {
+                    (
+                        *action,
+                        motion::ActionEntry::new(
+                            entry.entries().cloned().collect(),
+                            entry.settings,
+                        ),
+                    )
+                })
+                .collect(),
+            held_buttons: HashSet::<button::Variant>::new(),
+        }
+    }
+
+    /**
+     * Takes over the bindings and triggers of source.
+     * Entries whose bindings did not change keep their state,
+     * so rebinding one action does not drop the presses of the others
+     */
+    fn sync_bindings(&mut self, source: &SlotInput) {
+        let mut synced = source.bindings_copy();
+        for (action, entry) in synced.button_entries.iter_mut() {
+            if let Some(kept) = self.button_entries.remove(action).filter(|kept| {
+                kept.trigger == entry.trigger && kept.bindings().eq(entry.bindings())
+            }) {
+                *entry = kept;
+            }
+        }
+        for (action, entry) in synced.motion_entries.iter_mut() {
+            if let Some(mut kept) = self
+                .motion_entries
+                .remove(action)
+                .filter(|kept| kept.entries().eq(entry.entries()))
+            {
+                kept.settings = entry.settings;
+                *entry = kept;
+            }
+        }
+        synced.held_buttons = std::mem::take(&mut self.held_buttons);
+        *self = synced;
+    }
+
+    pub fn get_motion(&self, action: Action) -> motion::Motion {
+        if let Some(entry) = self.motion_entries.get(&action) {
+            return entry.output;
+        }
+        unreachable!("Missing action: {}", action.0)
     }
 
     pub fn is_action_pressed(&self, action: Action) -> bool {
         if let Some(entry) = self.button_entries.get(&action) {
-            return !entry.pressed.is_empty();
+            return entry.is_engaged() && !entry.pressed.is_empty();
         }
         false
     }
 
     pub fn is_action_just_pressed(&self, action: Action) -> bool {
         if let Some(entry) = self.button_entries.get(&action) {
-            return !entry.just_pressed.is_empty();
+            return entry.is_engaged() && !entry.just_pressed.is_empty();
         }
         false
     }
 
     pub fn is_action_just_released(&self, action: Action) -> bool {
         if let Some(entry) = self.button_entries.get(&action) {
-            return !entry.just_released.is_empty();
+            return entry.is_engaged() && !entry.just_released.is_empty();
+        }
+        false
+    }
+
+    pub fn is_action_held_for(&self, action: Action, duration: Duration) -> bool {
+        if let Some(entry) = self.button_entries.get(&action) {
+            return entry.held_duration().is_some_and(|held| held >= duration);
         }
         false
     }
 
-    fn set_button_pressed(&mut self, button: button::Variant) {
+    /// Only for actions registered with TriggerKind::Hold, true the frame the threshold is reached
+    pub fn is_action_long_pressed(&self, action: Action) -> bool {
+        if let Some(entry) = self.button_entries.get(&action) {
+            return entry.timing.long_pressed;
+        }
+        false
+    }
+
+    /// Only for actions registered with TriggerKind::Hold, true on release before the threshold
+    pub fn is_action_tapped(&self, action: Action) -> bool {
+        if let Some(entry) = self.button_entries.get(&action) {
+            return entry.timing.tapped;
+        }
+        false
+    }
+
+    /// Only for actions registered with TriggerKind::DoubleTap, true on the second press
+    pub fn is_action_double_tapped(&self, action: Action) -> bool {
+        if let Some(entry) = self.button_entries.get(&action) {
+            return entry.timing.double_tapped;
+        }
+        false
+    }
+
+    fn update_button_triggers(&mut self, now: Duration) {
         for buttoninput in self.button_entries.values_mut() {
+            buttoninput.update_trigger(now, &self.held_buttons);
+        }
+    }
+
+    /**
+     * Only the receivers with the most modifiers held get the press,
+     * so an engaged Shift+K chord takes K from a plain K action
+     */
+    fn set_button_pressed(&mut self, button: button::Variant, receivers: &[Action]) {
+        self.held_buttons.insert(button);
+        let held = &self.held_buttons;
+        let most_specific = receivers
+            .iter()
+            .filter_map(|action| self.button_entries.get(action))
+            .map(|entry| entry.held_modifiers(held))
+            .max()
+            .unwrap_or(0);
+        for (_, buttoninput) in self.button_entries.iter_mut().filter(|(action, entry)| {
+            receivers.contains(action) && entry.held_modifiers(held) == most_specific
+        }) {
             for b in buttoninput.released.extract_if(|b| *b == button) {
                 buttoninput.just_pressed.insert(b);
             }
@@ -186,6 +537,7 @@ impl InputManager {
     }
 
     fn set_button_released(&mut self, button: button::Variant) {
+        self.held_buttons.remove(&button);
         for buttoninput in self.button_entries.values_mut() {
             for b in buttoninput.pressed.extract_if(|b| *b == button) {
                 buttoninput.just_released.insert(b);
@@ -208,19 +560,187 @@ pub mod button {
         prelude::*,
         utils::HashSet,
     };
+    use serde::{Deserialize, Serialize};
+    use std::time::Duration;
 
-    #[derive(PartialEq, Eq, Hash, Clone, Copy)]
+    use super::PlayerSlot;
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
     pub enum Variant {
         Keyboard(KeyCode),
         Mouse(MouseButton),
         Gamepad(GamepadButton),
+        // id of an on-screen TouchButton
+        Touch(u8),
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum TriggerKind {
+        Press,
+        /**
+         * Modifiers that must be held when one of the action buttons is pressed, e.g. Shift+K.
+         * Pressing the modifiers after the button does not engage the chord
+         */
+        Chord(Vec<Variant>),
+        // long press once held for the duration, tap if released before it
+        Hold(Duration),
+        // second press within the window after the first
+        DoubleTap(Duration),
+    }
+
+    #[derive(Default)]
+    pub(super) struct Timing {
+        now: Duration,
+        pressed_at: Option<Duration>,
+        last_press_at: Option<Duration>,
+        engaged: bool,
+        long_press_fired: bool,
+        pub long_pressed: bool,
+        pub tapped: bool,
+        pub double_tapped: bool,
     }
 
     pub(super) struct ActionEntry {
+        // every bound button in the order it was bound, the first is the one prompts show
+        bound: Vec<Variant>,
         pub just_pressed: HashSet<Variant>,
         pub pressed: HashSet<Variant>,
         pub just_released: HashSet<Variant>,
         pub released: HashSet<Variant>,
+        pub trigger: TriggerKind,
+        pub timing: Timing,
+    }
+
+    impl ActionEntry {
+        pub fn new(buttons: impl IntoIterator<Item = Variant>, trigger: TriggerKind) -> Self {
+            let mut bound = Vec::<Variant>::new();
+            for button in buttons {
+                if !bound.contains(&button) {
+                    bound.push(button);
+                }
+            }
+            Self {
+                just_pressed: HashSet::<Variant>::new(),
+                pressed: HashSet::<Variant>::new(),
+                just_released: HashSet::<Variant>::new(),
+                released: bound.iter().copied().collect::<HashSet<_>>(),
+                bound,
+                trigger,
+                timing: Timing {
+                    engaged: true,
+                    ..default()
+                },
+            }
+        }
+
+        // new buttons for the same trigger, any ongoing press is dropped
+        pub fn replace_bindings(&mut self, buttons: impl IntoIterator<Item = Variant>) {
+            *self = Self::new(buttons, self.trigger.clone());
+        }
+
+        // number of chord modifiers held, 0 unless all of them are
+        pub fn held_modifiers(&self, held: &HashSet<Variant>) -> usize {
+            match &self.trigger {
+                TriggerKind::Chord(modifiers) if modifiers.iter().all(|m| held.contains(m)) => {
+                    modifiers.len()
+                }
+                _ => 0,
+            }
+        }
+
+        // false while a chord action is pressed without its modifiers
+        pub fn is_engaged(&self) -> bool {
+            self.timing.engaged
+        }
+
+        pub fn held_duration(&self) -> Option<Duration> {
+            if !self.timing.engaged {
+                return None;
+            }
+            self.timing
+                .pressed_at
+                .map(|pressed_at| self.timing.now.saturating_sub(pressed_at))
+        }
+
+        /**
+         * Run once per frame after all button events are applied.
+         * held contains every raw button currently down, bound or not
+         */
+        pub fn update_trigger(&mut self, now: Duration, held: &HashSet<Variant>) {
+            let timing = &mut self.timing;
+            timing.now = now;
+            timing.long_pressed = false;
+            timing.tapped = false;
+            timing.double_tapped = false;
+
+            let is_down = !self.just_pressed.is_empty() || !self.pressed.is_empty();
+
+            // first button of the action going down, others are ignored while it is held
+            if !self.just_pressed.is_empty() && timing.pressed_at.is_none() {
+                timing.pressed_at = Some(now);
+                timing.long_press_fired = false;
+                timing.engaged = match &self.trigger {
+                    TriggerKind::Chord(modifiers) => modifiers.iter().all(|m| held.contains(m)),
+                    _ => true,
+                };
+
+                if let TriggerKind::DoubleTap(window) = self.trigger {
+                    if timing
+                        .last_press_at
+                        .is_some_and(|last| now.saturating_sub(last) <= window)
+                    {
+                        timing.double_tapped = true;
+                        // a third press starts a new double tap
+                        timing.last_press_at = None;
+                    } else {
+                        timing.last_press_at = Some(now);
+                    }
+                }
+            }
+
+            let Some(pressed_at) = timing.pressed_at else {
+                return;
+            };
+            let held_for = now.saturating_sub(pressed_at);
+
+            if let TriggerKind::Hold(threshold) = self.trigger {
+                if is_down && !timing.long_press_fired && held_for >= threshold {
+                    timing.long_pressed = true;
+                    timing.long_press_fired = true;
+                }
+                if !is_down && held_for < threshold {
+                    timing.tapped = true;
+                }
+            }
+
+            if !is_down {
+                timing.pressed_at = None;
+            }
+        }
+
+        // every bound button in binding order, regardless of its current state
+        pub fn bindings(&self) -> impl Iterator<Item = &Variant> {
+            self.bound.iter()
+        }
+
+        pub fn is_bound(&self, button: Variant) -> bool {
+            self.bound.contains(&button)
+        }
+
+        pub fn bind(&mut self, button: Variant) {
+            if !self.is_bound(button) {
+                self.bound.push(button);
+                self.released.insert(button);
+            }
+        }
+
+        pub fn unbind(&mut self, button: Variant) -> bool {
+            self.bound.retain(|b| *b != button);
+            self.just_pressed.remove(&button)
+                | self.pressed.remove(&button)
+                | self.just_released.remove(&button)
+                | self.released.remove(&button)
+        }
     }
 
     pub(super) fn read_button_input(
@@ -228,40 +748,49 @@ pub mod button {
         mut mouse: EventReader<MouseButtonInput>,
         mut gamepad: EventReader<GamepadEvent>,
         mut input_manager: ResMut<super::InputManager>,
+        time: Res<Time<Real>>,
     ) {
-        input_manager.move_prev_frame_just_pressed();
-        input_manager.move_prev_frame_just_released();
+        input_manager.move_prev_frame();
 
         for key in keyboard.get_just_pressed() {
-            input_manager.set_button_pressed(Variant::Keyboard(*key));
+            input_manager.set_button_pressed(PlayerSlot::KeyboardMouse, Variant::Keyboard(*key));
         }
         for key in keyboard.get_just_released() {
-            input_manager.set_button_released(Variant::Keyboard(*key));
+            input_manager.set_button_released(PlayerSlot::KeyboardMouse, Variant::Keyboard(*key));
         }
 
         for event in mouse.read() {
             match event.state {
-                ButtonState::Pressed => {
-                    input_manager.set_button_pressed(Variant::Mouse(event.button))
-                }
-                ButtonState::Released => {
-                    input_manager.set_button_released(Variant::Mouse(event.button))
-                }
+                ButtonState::Pressed => input_manager
+                    .set_button_pressed(PlayerSlot::KeyboardMouse, Variant::Mouse(event.button)),
+                ButtonState::Released => input_manager
+                    .set_button_released(PlayerSlot::KeyboardMouse, Variant::Mouse(event.button)),
             }
         }
 
         for event in gamepad.read() {
             match event {
                 GamepadEvent::Button(button) => {
+                    let slot = PlayerSlot::Gamepad(button.entity);
                     if button.state.is_pressed() {
-                        input_manager.set_button_pressed(Variant::Gamepad(button.button));
+                        input_manager.set_button_pressed(slot, Variant::Gamepad(button.button));
                     } else {
-                        input_manager.set_button_released(Variant::Gamepad(button.button));
+                        input_manager.set_button_released(slot, Variant::Gamepad(button.button));
                     }
                 }
                 _ => (),
             }
         }
+
+        let touch_button_events = std::mem::take(&mut input_manager.touch_button_events);
+        for (id, pressed) in touch_button_events {
+            if pressed {
+                input_manager.set_button_pressed(PlayerSlot::Touch, Variant::Touch(id));
+            } else {
+                input_manager.set_button_released(PlayerSlot::Touch, Variant::Touch(id));
+            }
+        }
+        input_manager.update_button_triggers(time.elapsed());
     }
 }
 
@@ -269,16 +798,20 @@ pub mod motion {
     use bevy::{
         input::{
             gamepad::{GamepadAxisChangedEvent, GamepadEvent},
-            mouse::AccumulatedMouseMotion,
+            mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll},
             ButtonInput,
         },
         math::{Quat, Vec2, Vec3},
         prelude::{EventReader, GamepadAxis, KeyCode, Res, ResMut},
         utils::HashSet,
     };
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    use super::{InputMode, PlayerSlot};
 
     #[derive(Default, Debug, Clone, Copy)]
-    pub struct Motion(Vec2);
+    pub struct Motion(pub(super) Vec2);
     impl Motion {
         pub const fn raw_vec2(&self) -> Vec2 {
             self.0
@@ -304,16 +837,17 @@ pub mod motion {
         pub fn get_motion_y(&self, rotation: f32) -> Vec3 {
             Quat::from_rotation_y(rotation) * self.get_y_dir()
         }
+        // analog noise is removed by the action's dead zone, see MotionSettings
         pub fn get_motion_opt_y(&self, rotation: f32) -> Option<Vec3> {
             let dir = self.get_y_dir();
-            if dir.length() < 0.1 {
+            if dir == Vec3::ZERO {
                 return None;
             }
             Some(Quat::from_rotation_y(rotation) * self.get_y_dir())
         }
     }
 
-    #[derive(Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
     pub enum Axis {
         X,
         Y,
@@ -343,16 +877,27 @@ pub mod motion {
         }
     }
 
-    #[derive(Clone)]
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
     pub enum Relation {
         GamepadAxis(GamepadAxis, Axis),
         Mouse(
             // acts as sensitivity, mouse motion is dividied by this
             f32,
         ),
+        // scroll lines/pixels are multiplied by this, vertical wheel is y
+        MouseWheel(f32),
+        // id of an on-screen VirtualStick
+        TouchStick(u8),
         KeyCode(KeyCode, Axis),
     }
 
+    #[derive(Default, Clone, Copy)]
+    pub(super) struct MouseDelta {
+        motion: Option<Vec2>,
+        scroll: Option<Vec2>,
+    }
+
+    #[derive(Default)]
     pub(super) struct KeyCodeSet {
         pressed: HashSet<KeyCode>,
         released: HashSet<KeyCode>,
@@ -370,14 +915,121 @@ pub mod motion {
         fn is_empty(&self) -> bool {
             return self.pressed.is_empty() && self.released.is_empty();
         }
+
+        // keys consumed by a higher input context
+        fn without(&self, hidden: &HashSet<KeyCode>) -> Self {
+            Self {
+                pressed: self.pressed.difference(hidden).copied().collect(),
+                released: self.released.difference(hidden).copied().collect(),
+            }
+        }
     }
 
-    #[derive(Clone)]
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
     pub struct Entry {
         pub input_type: super::InputType,
         pub relations: Vec<Relation>,
     }
 
+    #[derive(Debug, Clone, Copy)]
+    pub enum DeadZone {
+        None,
+        // each stick axis on its own, keeps snapping to the cardinal directions
+        Axial { inner: f32, outer: f32 },
+        // on the stick vector length, keeps the direction
+        Radial { inner: f32, outer: f32 },
+    }
+
+    impl DeadZone {
+        fn remap(value: f32, inner: f32, outer: f32) -> f32 {
+            ((value - inner) / (outer - inner).max(f32::EPSILON)).clamp(0., 1.)
+        }
+
+        fn apply(&self, stick: Vec2) -> Vec2 {
+            match *self {
+                Self::None => stick,
+                Self::Axial { inner, outer } => Vec2::new(
+                    stick.x.signum() * Self::remap(stick.x.abs(), inner, outer),
+                    stick.y.signum() * Self::remap(stick.y.abs(), inner, outer),
+                ),
+                Self::Radial { inner, outer } => {
+                    let length = stick.length();
+                    if length <= inner {
+                        return Vec2::ZERO;
+                    }
+                    stick / length * Self::remap(length, inner, outer)
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub enum ResponseCurve {
+        Linear,
+        Quadratic,
+        // maps stick magnitude in [0, 1] to output magnitude in [0, 1]
+        Custom(fn(f32) -> f32),
+    }
+
+    impl ResponseCurve {
+        fn apply(&self, magnitude: f32) -> f32 {
+            match self {
+                Self::Linear => magnitude,
+                Self::Quadratic => magnitude * magnitude,
+                Self::Custom(curve) => curve(magnitude).clamp(0., 1.),
+            }
+        }
+    }
+
+    /**
+     * Per action shaping of the motion vector.
+     * Dead zone and curve only touch gamepad sticks, sensitivity and invert apply to every device
+     */
+    #[derive(Debug, Clone, Copy)]
+    pub struct MotionSettings {
+        pub dead_zone: DeadZone,
+        pub curve: ResponseCurve,
+        pub sensitivity: Vec2,
+        pub invert_x: bool,
+        pub invert_y: bool,
+    }
+
+    impl Default for MotionSettings {
+        fn default() -> Self {
+            Self {
+                dead_zone: DeadZone::Radial {
+                    inner: 0.1,
+                    outer: 0.95,
+                },
+                curve: ResponseCurve::Linear,
+                sensitivity: Vec2::ONE,
+                invert_x: false,
+                invert_y: false,
+            }
+        }
+    }
+
+    impl MotionSettings {
+        fn shape_stick(&self, stick: Vec2) -> Vec2 {
+            let stick = self.dead_zone.apply(stick);
+            let length = stick.length();
+            if length == 0. {
+                return Vec2::ZERO;
+            }
+            // radial clamp, axial dead zones can reach sqrt(2) in the corners
+            let length = length.min(1.);
+            stick.normalize() * self.curve.apply(length)
+        }
+
+        fn scale(&self, motion: Vec2) -> Vec2 {
+            let invert = Vec2::new(
+                if self.invert_x { -1. } else { 1. },
+                if self.invert_y { -1. } else { 1. },
+            );
+            motion * self.sensitivity * invert
+        }
+    }
+
     pub struct ActionEntry {
         /**
          * bool -- motion_last_frame
@@ -385,16 +1037,55 @@ pub mod motion {
          * then the entry might want to overwrite the motion vector to Vec2::ZERO
          */
         pub motion_entries: Vec<(Entry, bool)>,
+        // unshaped motion as written by the entries
         pub motion: Motion,
+        // last gamepad axis values, axis events only arrive on change
+        pub stick: Vec2,
+        pub settings: MotionSettings,
+        // what get_motion returns
+        pub output: Motion,
     }
 
     impl ActionEntry {
+        pub fn new(entries: Vec<Entry>, settings: MotionSettings) -> Self {
+            Self {
+                motion_entries: entries.into_iter().map(|e| (e, false)).collect(),
+                motion: Motion::default(),
+                stick: Vec2::ZERO,
+                settings,
+                output: Motion::default(),
+            }
+        }
+
+        // new entries for the same settings
+        pub fn replace_entries(&mut self, entries: Vec<Entry>) {
+            *self = Self::new(entries, self.settings);
+        }
+
+        /**
+         * Back to rest, for while the context of the action is inactive.
+         * Keys let go in the meantime would otherwise keep their motion, as nothing pressed writes none
+         */
+        pub(super) fn reset(&mut self) {
+            for (_, motion_last_frame) in self.motion_entries.iter_mut() {
+                *motion_last_frame = false;
+            }
+            self.motion = Motion::default();
+            self.stick = Vec2::ZERO;
+            self.output = Motion::default();
+        }
+
+        pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+            self.motion_entries.iter().map(|(e, _)| e)
+        }
+
         pub(super) fn set_motion(
             &mut self,
             input_mode_priority: super::InputMode,
             axis_events: &Vec<GamepadAxisChangedEvent>,
-            mouse_motion: &Option<Vec2>,
+            mouse: &MouseDelta,
             keyboard: &KeyCodeSet,
+            touch_sticks: &HashMap<u8, Vec2>,
         ) {
             for (mapping, motion_last_frame) in self
                 .motion_entries
@@ -402,34 +1093,47 @@ pub mod motion {
                 .filter(|(m, _)| m.input_type.is_mode(input_mode_priority))
             {
                 match mapping.input_type {
-                    super::InputType::Gamepad => Self::set_gamepad_axis_motion(
-                        &mut self.motion.get_mut(),
-                        &mapping.relations,
-                        axis_events,
-                    ),
+                    super::InputType::Gamepad => {
+                        if Self::set_gamepad_axis_motion(
+                            &mut self.stick,
+                            &mapping.relations,
+                            axis_events,
+                        ) {
+                            *self.motion.get_mut() = self.settings.shape_stick(self.stick);
+                        }
+                    }
                     super::InputType::Keyboard => Self::set_keyboard_motion(
                         &mut self.motion.get_mut(),
                         &mapping.relations,
                         keyboard,
                     ),
                     super::InputType::Mouse => {
-                        Self::set_mouse_motion(
+                        *motion_last_frame = Self::set_mouse_motion(
                             &mut self.motion.get_mut(),
                             *motion_last_frame,
                             &mapping.relations,
-                            mouse_motion,
+                            mouse,
                         );
-                        *motion_last_frame = mouse_motion.is_some();
+                    }
+                    super::InputType::Touch => {
+                        if let Some(stick) = Self::get_touch_stick(&mapping.relations, touch_sticks)
+                        {
+                            *self.motion.get_mut() = self.settings.shape_stick(stick);
+                        }
                     }
                 };
             }
+
+            self.output = Motion(self.settings.scale(self.motion.raw_vec2()));
         }
 
+        // returns true if any axis of the relations changed
         fn set_gamepad_axis_motion(
-            motion: &mut Vec2,
+            stick: &mut Vec2,
             relations: &Vec<Relation>,
             axis_events: &Vec<GamepadAxisChangedEvent>,
-        ) {
+        ) -> bool {
+            let mut changed = false;
             for relation in relations {
                 if let Relation::GamepadAxis(relation_gamepad_axis, relation_axis) = relation {
                     for gamepad_event in axis_events
@@ -437,13 +1141,15 @@ pub mod motion {
                         .filter(|a| a.axis == *relation_gamepad_axis)
                     {
                         match relation_axis {
-                            Axis::X => motion.x = gamepad_event.value,
-                            Axis::Y => motion.y = gamepad_event.value,
-                            _ => (),
+                            Axis::X => stick.x = gamepad_event.value,
+                            Axis::Y => stick.y = gamepad_event.value,
+                            _ => continue,
                         }
+                        changed = true;
                     }
                 }
             }
+            changed
         }
 
         fn set_keyboard_motion(
@@ -473,25 +1179,50 @@ pub mod motion {
             *motion = new_motion;
         }
 
+        // virtual sticks are analog, they go through the same shaping as gamepad sticks
+        fn get_touch_stick(
+            relations: &Vec<Relation>,
+            touch_sticks: &HashMap<u8, Vec2>,
+        ) -> Option<Vec2> {
+            relations.iter().find_map(|relation| match relation {
+                Relation::TouchStick(id) => Some(touch_sticks.get(id).copied().unwrap_or_default()),
+                _ => None,
+            })
+        }
+
+        // returns true if the mouse moved or scrolled for any of the relations
         fn set_mouse_motion(
             motion: &mut Vec2,
             motion_last_frame: bool,
             relations: &Vec<Relation>,
-            mouse_motion: &Option<Vec2>,
-        ) {
-            assert!(relations.len() == 1);
-            if let Relation::Mouse(normalizing_factor) = relations[0] {
-                if let Some(mouse_motion) = mouse_motion {
-                    *motion = mouse_motion / normalizing_factor
-                } else if motion_last_frame {
-                    *motion = Vec2::ZERO
+            mouse: &MouseDelta,
+        ) -> bool {
+            let mut new_motion: Option<Vec2> = None;
+            for relation in relations {
+                let delta = match *relation {
+                    Relation::Mouse(normalizing_factor) => {
+                        mouse.motion.map(|m| m / normalizing_factor)
+                    }
+                    Relation::MouseWheel(scale) => mouse.scroll.map(|s| s * scale),
+                    _ => None,
+                };
+                if let Some(delta) = delta {
+                    *new_motion.get_or_insert(Vec2::ZERO) += delta;
                 }
             }
+
+            if let Some(new_motion) = new_motion {
+                *motion = new_motion;
+            } else if motion_last_frame {
+                *motion = Vec2::ZERO
+            }
+            new_motion.is_some()
         }
     }
 
     pub(super) fn read_motion_input(
         mouse_motion: Res<AccumulatedMouseMotion>,
+        mouse_scroll: Res<AccumulatedMouseScroll>,
         keyboard: Res<ButtonInput<KeyCode>>,
         mut gamepad: EventReader<GamepadEvent>,
         mut input_manager: ResMut<super::InputManager>,
@@ -506,12 +1237,9 @@ pub mod motion {
             events
         };
 
-        let mouse_motion = {
-            if mouse_motion.delta != Vec2::ZERO {
-                Some(mouse_motion.delta)
-            } else {
-                None
-            }
+        let mouse = MouseDelta {
+            motion: (mouse_motion.delta != Vec2::ZERO).then_some(mouse_motion.delta),
+            scroll: (mouse_scroll.delta != Vec2::ZERO).then_some(mouse_scroll.delta),
         };
 
         let keycodes = KeyCodeSet {
@@ -526,10 +1254,65 @@ pub mod motion {
                 .cloned()
                 .collect::<HashSet<KeyCode>>(),
         };
+        let no_keycodes = KeyCodeSet::default();
 
+        let visibility = input_manager.motion_key_visibility();
+        let input_manager = &mut *input_manager;
         let mode = input_manager.current_input_mode;
-        for action_entry in input_manager.motion_entries.values_mut() {
-            action_entry.set_motion(mode, &gamepad_axis_events, &mouse_motion, &keycodes);
+        for (action, action_entry) in input_manager.actions.motion_entries.iter_mut() {
+            // actions of inactive contexts stay at rest
+            let Some(Some(hidden)) = visibility.get(action) else {
+                action_entry.reset();
+                continue;
+            };
+            let keycodes = keycodes.without(hidden);
+            action_entry.set_motion(
+                mode,
+                &gamepad_axis_events,
+                &mouse,
+                &keycodes,
+                &input_manager.touch_sticks,
+            );
+        }
+
+        // each slot only sees its own devices
+        let no_touch_sticks = HashMap::<u8, Vec2>::new();
+        for (slot, input) in input_manager.slots.iter_mut() {
+            let (mode, axis_events, mouse, keycodes, touch_sticks) = match slot {
+                PlayerSlot::KeyboardMouse => (
+                    InputMode::MouseAndKeyboard,
+                    Vec::<GamepadAxisChangedEvent>::new(),
+                    mouse,
+                    &keycodes,
+                    &no_touch_sticks,
+                ),
+                PlayerSlot::Touch => (
+                    InputMode::Touch,
+                    Vec::<GamepadAxisChangedEvent>::new(),
+                    MouseDelta::default(),
+                    &no_keycodes,
+                    &input_manager.touch_sticks,
+                ),
+                PlayerSlot::Gamepad(gamepad) => (
+                    InputMode::Gamepad,
+                    gamepad_axis_events
+                        .iter()
+                        .filter(|e| e.entity == *gamepad)
+                        .cloned()
+                        .collect(),
+                    MouseDelta::default(),
+                    &no_keycodes,
+                    &no_touch_sticks,
+                ),
+            };
+            for (action, action_entry) in input.motion_entries.iter_mut() {
+                let Some(Some(hidden)) = visibility.get(action) else {
+                    action_entry.reset();
+                    continue;
+                };
+                let keycodes = keycodes.without(hidden);
+                action_entry.set_motion(mode, &axis_events, &mouse, &keycodes, touch_sticks);
+            }
         }
     }
 }
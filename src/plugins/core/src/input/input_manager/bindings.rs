@@ -0,0 +1,251 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{button, motion, Action, InputManager};
+
+const CONFIG_DIR: &str = "sporomancer";
+const BINDINGS_FILE: &str = "bindings.ron";
+
+#[derive(Debug)]
+pub enum BindingError {
+    UnknownAction(String),
+    NotBound(Action, button::Variant),
+    Conflict(button::Variant, Vec<Action>),
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownAction(name) => write!(f, "unknown action \"{name}\""),
+            Self::NotBound(action, button) => {
+                write!(f, "{button:?} is not bound to action \"{}\"", action.0)
+            }
+            Self::Conflict(button, actions) => {
+                let names = actions.iter().map(|a| a.0).collect::<Vec<_>>();
+                write!(f, "{button:?} is already bound to {names:?}")
+            }
+            Self::Io(err) => write!(f, "bindings file: {err}"),
+            Self::Parse(err) => write!(f, "bindings file: {err}"),
+            Self::Serialize(err) => write!(f, "bindings file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BindingError {}
+
+/**
+ * On-disk representation of every registered binding, keyed by action name.
+ * Actions are looked up by name among the registered ones when loading,
+ * so registration in Startup must happen before a file can be applied.
+ */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BindingsFile {
+    pub buttons: BTreeMap<String, Vec<button::Variant>>,
+    pub motions: BTreeMap<String, Vec<motion::Entry>>,
+}
+
+/// Trigger to write the current bindings to the user config dir
+#[derive(Event)]
+pub struct SaveBindings;
+
+pub fn user_bindings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(BINDINGS_FILE))
+}
+
+impl InputManager {
+    fn find_button_action(&self, name: &str) -> Result<Action, BindingError> {
+        self.actions
+            .button_entries
+            .keys()
+            .find(|a| a.0 == name)
+            .copied()
+            .ok_or_else(|| BindingError::UnknownAction(name.to_string()))
+    }
+
+    fn find_motion_action(&self, name: &str) -> Result<Action, BindingError> {
+        self.actions
+            .motion_entries
+            .keys()
+            .find(|a| a.0 == name)
+            .copied()
+            .ok_or_else(|| BindingError::UnknownAction(name.to_string()))
+    }
+
+    pub fn get_button_bindings(&self, action: Action) -> Vec<button::Variant> {
+        self.actions
+            .button_entries
+            .get(&action)
+            .map(|e| e.bindings().copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get_motion_bindings(&self, action: Action) -> Vec<motion::Entry> {
+        self.actions
+            .motion_entries
+            .get(&action)
+            .map(|e| e.entries().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Other actions that already listen to the given button
+    pub fn get_button_conflicts(&self, action: Action, button: button::Variant) -> Vec<Action> {
+        self.actions
+            .button_entries
+            .iter()
+            .filter(|(a, e)| **a != action && e.is_bound(button))
+            .map(|(a, _)| *a)
+            .collect()
+    }
+
+    pub fn add_button_binding(
+        &mut self,
+        action: Action,
+        button: button::Variant,
+    ) -> Result<(), BindingError> {
+        let conflicts = self.get_button_conflicts(action, button);
+        if !conflicts.is_empty() {
+            return Err(BindingError::Conflict(button, conflicts));
+        }
+        let Some(entry) = self.actions.button_entries.get_mut(&action) else {
+            return Err(BindingError::UnknownAction(action.0.to_string()));
+        };
+        entry.bind(button);
+        self.sync_slots();
+        Ok(())
+    }
+
+    pub fn remove_button_binding(
+        &mut self,
+        action: Action,
+        button: button::Variant,
+    ) -> Result<(), BindingError> {
+        let Some(entry) = self.actions.button_entries.get_mut(&action) else {
+            return Err(BindingError::UnknownAction(action.0.to_string()));
+        };
+        if !entry.unbind(button) {
+            return Err(BindingError::NotBound(action, button));
+        }
+        self.sync_slots();
+        Ok(())
+    }
+
+    pub fn replace_button_binding(
+        &mut self,
+        action: Action,
+        old: button::Variant,
+        new: button::Variant,
+    ) -> Result<(), BindingError> {
+        let conflicts = self.get_button_conflicts(action, new);
+        if !conflicts.is_empty() {
+            return Err(BindingError::Conflict(new, conflicts));
+        }
+        self.remove_button_binding(action, old)?;
+        self.add_button_binding(action, new)
+    }
+
+    pub fn replace_motion_bindings(
+        &mut self,
+        action: Action,
+        entries: Vec<motion::Entry>,
+    ) -> Result<(), BindingError> {
+        let Some(entry) = self.actions.motion_entries.get_mut(&action) else {
+            return Err(BindingError::UnknownAction(action.0.to_string()));
+        };
+        entry.replace_entries(entries);
+        self.sync_slots();
+        Ok(())
+    }
+
+    pub fn to_bindings_file(&self) -> BindingsFile {
+        BindingsFile {
+            buttons: self
+                .button_entries
+                .keys()
+                .map(|a| (a.0.to_string(), self.get_button_bindings(*a)))
+                .collect(),
+            motions: self
+                .motion_entries
+                .keys()
+                .map(|a| (a.0.to_string(), self.get_motion_bindings(*a)))
+                .collect(),
+        }
+    }
+
+    /**
+     * All actions in the file are resolved before anything is applied,
+     * so a file with an unknown action leaves the current bindings untouched
+     */
+    pub fn apply_bindings_file(&mut self, file: BindingsFile) -> Result<(), BindingError> {
+        let buttons = file
+            .buttons
+            .into_iter()
+            .map(|(name, b)| Ok((self.find_button_action(&name)?, b)))
+            .collect::<Result<Vec<_>, BindingError>>()?;
+        let motions = file
+            .motions
+            .into_iter()
+            .map(|(name, m)| Ok((self.find_motion_action(&name)?, m)))
+            .collect::<Result<Vec<_>, BindingError>>()?;
+
+        for (action, b) in buttons {
+            if let Some(entry) = self.actions.button_entries.get_mut(&action) {
+                entry.replace_bindings(b);
+            }
+        }
+        for (action, m) in motions {
+            if let Some(entry) = self.actions.motion_entries.get_mut(&action) {
+                entry.replace_entries(m);
+            }
+        }
+        self.sync_slots();
+        Ok(())
+    }
+
+    pub fn load_bindings(&mut self, path: &Path) -> Result<(), BindingError> {
+        let contents = fs::read_to_string(path).map_err(BindingError::Io)?;
+        let file = ron::from_str::<BindingsFile>(&contents).map_err(BindingError::Parse)?;
+        self.apply_bindings_file(file)
+    }
+
+    pub fn save_bindings(&self, path: &Path) -> Result<(), BindingError> {
+        let contents =
+            ron::ser::to_string_pretty(&self.to_bindings_file(), ron::ser::PrettyConfig::default())
+                .map_err(BindingError::Serialize)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(BindingError::Io)?;
+        }
+        fs::write(path, contents).map_err(BindingError::Io)
+    }
+}
+
+pub(super) fn load_user_bindings(mut input_manager: ResMut<InputManager>) {
+    let Some(path) = user_bindings_path() else {
+        return;
+    };
+    if !path.exists() {
+        return;
+    }
+    match input_manager.load_bindings(&path) {
+        Ok(()) => info!("loaded input bindings from {}", path.display()),
+        Err(err) => error!("could not load input bindings: {err}"),
+    }
+}
+
+pub(super) fn save_user_bindings(_: Trigger<SaveBindings>, input_manager: Res<InputManager>) {
+    let Some(path) = user_bindings_path() else {
+        warn!("no user config dir, input bindings not saved");
+        return;
+    };
+    match input_manager.save_bindings(&path) {
+        Ok(()) => info!("saved input bindings to {}", path.display()),
+        Err(err) => error!("could not save input bindings: {err}"),
+    }
+}
@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+use super::{Action, InputManager};
+
+impl InputManager {
+    /**
+     * Keep presses of the action around for the window, so a press that lands
+     * while its receiver is not listening (e.g. mid state change) can be consumed later
+     */
+    pub fn set_action_buffer(&mut self, action: Action, window: Duration) {
+        self.buffer_windows.insert(action, window);
+    }
+
+    pub fn is_press_buffered(&self, action: Action) -> bool {
+        self.buffered_presses.contains_key(&action)
+    }
+
+    /// True once per buffered press, if it is still within the buffer window
+    pub fn consume_buffered_press(&mut self, action: Action) -> bool {
+        self.buffered_presses.remove(&action).is_some()
+    }
+}
+
+// after replay, so replayed presses are buffered the same way
+pub(super) fn buffer_presses(mut input_manager: ResMut<InputManager>, time: Res<Time<Real>>) {
+    let now = time.elapsed();
+    let pressed = input_manager
+        .buffer_windows
+        .keys()
+        .filter(|action| input_manager.is_action_just_pressed(**action))
+        .copied()
+        .collect::<Vec<_>>();
+
+    let input_manager = &mut *input_manager;
+    for action in pressed {
+        input_manager.buffered_presses.insert(action, now);
+    }
+    input_manager.buffered_presses.retain(|action, pressed_at| {
+        input_manager
+            .buffer_windows
+            .get(action)
+            .is_some_and(|window| now.saturating_sub(*pressed_at) <= *window)
+    });
+}
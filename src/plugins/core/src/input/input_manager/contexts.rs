@@ -0,0 +1,250 @@
+use bevy::{prelude::*, utils::HashSet};
+use std::collections::HashMap;
+
+use super::{button, motion, Action, InputManager};
+
+/**
+ * Named set of actions that are live together, e.g. gameplay or a menu.
+ * Contexts are stacked, the top one sees input first and consumes every button
+ * bound in it, so contexts further down never see that button
+ */
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct InputContext(pub &'static str);
+
+impl InputContext {
+    // always at the bottom of the stack, cannot be popped or hidden
+    pub const GLOBAL: Self = Self("global");
+    // actions without an explicit context
+    pub const GAMEPLAY: Self = Self("gameplay");
+    pub const MENU: Self = Self("menu");
+    pub const EDITOR: Self = Self("editor");
+    pub const CINEMATIC: Self = Self("cinematic");
+    pub const PHOTO: Self = Self("photo");
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ContextLayer {
+    context: InputContext,
+    // nothing below an exclusive layer but the GLOBAL base sees input, bound or not
+    exclusive: bool,
+}
+
+pub(super) fn default_stack() -> Vec<ContextLayer> {
+    vec![
+        ContextLayer {
+            context: InputContext::GLOBAL,
+            exclusive: false,
+        },
+        ContextLayer {
+            context: InputContext::GAMEPLAY,
+            exclusive: false,
+        },
+    ]
+}
+
+impl InputManager {
+    pub fn push_context(&mut self, context: InputContext, exclusive: bool) {
+        self.context_stack.push(ContextLayer { context, exclusive });
+    }
+
+    pub fn pop_context(&mut self) -> Option<InputContext> {
+        if self.context_stack.len() <= 1 {
+            return None;
+        }
+        self.context_stack.pop().map(|layer| layer.context)
+    }
+
+    /**
+     * Removes the topmost layer of context wherever it is in the stack,
+     * for layers that may have been covered by another one since they were pushed.
+     * The GLOBAL base layer stays, false if there was nothing to remove
+     */
+    pub fn remove_context(&mut self, context: InputContext) -> bool {
+        let Some(index) = self
+            .context_stack
+            .iter()
+            .skip(1)
+            .rposition(|layer| layer.context == context)
+        else {
+            return false;
+        };
+        self.context_stack.remove(index + 1);
+        true
+    }
+
+    pub fn top_context(&self) -> InputContext {
+        self.context_stack
+            .last()
+            .map(|layer| layer.context)
+            .unwrap_or(InputContext::GLOBAL)
+    }
+
+    pub fn is_context_active(&self, context: InputContext) -> bool {
+        self.active_layers().any(|layer| layer.context == context)
+    }
+
+    pub fn set_action_context(&mut self, action: Action, context: InputContext) {
+        self.action_contexts.insert(action, context);
+        self.report_overlaps(action);
+    }
+
+    pub fn get_action_context(&self, action: Action) -> InputContext {
+        self.action_contexts
+            .get(&action)
+            .copied()
+            .unwrap_or(InputContext::GAMEPLAY)
+    }
+
+    /**
+     * Top to bottom, stops after the first exclusive layer.
+     * The GLOBAL base layer is always active, so e.g. quitting works in every context
+     */
+    fn active_layers(&self) -> impl Iterator<Item = &ContextLayer> {
+        let (base, layers) = self.context_stack.split_at(self.context_stack.len().min(1));
+        let mut blocked = false;
+        layers
+            .iter()
+            .rev()
+            .take_while(move |layer| {
+                let visible = !blocked;
+                blocked |= layer.exclusive;
+                visible
+            })
+            .chain(base)
+    }
+
+    // keys of both button and motion bindings, motion keys act as buttons for overlaps
+    fn context_bindings(&self, context: InputContext) -> Vec<(Action, button::Variant)> {
+        let buttons = self
+            .actions
+            .button_entries
+            .iter()
+            .filter(|(action, _)| self.get_action_context(**action) == context)
+            .flat_map(|(action, entry)| entry.bindings().map(|b| (*action, *b)));
+        let motion_keys = self
+            .actions
+            .motion_entries
+            .iter()
+            .filter(|(action, _)| self.get_action_context(**action) == context)
+            .flat_map(|(action, entry)| {
+                entry
+                    .entries()
+                    .flat_map(|e| e.relations.iter())
+                    .filter_map(|relation| match relation {
+                        motion::Relation::KeyCode(key, _) => Some(button::Variant::Keyboard(*key)),
+                        _ => None,
+                    })
+                    .map(|b| (*action, b))
+                    .collect::<Vec<_>>()
+            });
+        buttons.chain(motion_keys).collect()
+    }
+
+    /// Pairs of actions in the same context that listen to the same button or key
+    pub fn get_context_overlaps(
+        &self,
+        context: InputContext,
+    ) -> Vec<(Action, Action, button::Variant)> {
+        let bindings = self.context_bindings(context);
+        let mut overlaps = Vec::new();
+        for (i, (action, variant)) in bindings.iter().enumerate() {
+            for (other, other_variant) in bindings.iter().skip(i + 1) {
+                if variant == other_variant && action != other {
+                    overlaps.push((*action, *other, *variant));
+                }
+            }
+        }
+        overlaps
+    }
+
+    pub(super) fn report_overlaps(&self, action: Action) {
+        let context = self.get_action_context(action);
+        for (action, other, variant) in self
+            .get_context_overlaps(context)
+            .into_iter()
+            .filter(|(a, b, _)| *a == action || *b == action)
+        {
+            warn!(
+                "input context \"{}\": {variant:?} is bound to both \"{}\" and \"{}\"",
+                context.0, action.0, other.0
+            );
+        }
+    }
+
+    /// Actions that get a press of this button, all from the topmost context that binds it
+    pub(super) fn button_receivers(&self, button: button::Variant) -> Vec<Action> {
+        for layer in self.active_layers() {
+            let receivers = self
+                .actions
+                .button_entries
+                .iter()
+                .filter(|(action, entry)| {
+                    self.get_action_context(**action) == layer.context && entry.is_bound(button)
+                })
+                .map(|(action, _)| *action)
+                .collect::<Vec<_>>();
+            if !receivers.is_empty() {
+                return receivers;
+            }
+        }
+        Vec::new()
+    }
+
+    /**
+     * Keys hidden from each motion action by contexts above it.
+     * None for motion actions whose context is not active
+     */
+    pub(super) fn motion_key_visibility(&self) -> HashMap<Action, Option<HashSet<KeyCode>>> {
+        let mut consumed = HashSet::<KeyCode>::new();
+        let mut visibility = HashMap::new();
+        for layer in self.active_layers() {
+            let bindings = self.context_bindings(layer.context);
+            for action in self
+                .actions
+                .motion_entries
+                .keys()
+                .filter(|action| self.get_action_context(**action) == layer.context)
+            {
+                visibility.insert(*action, Some(consumed.clone()));
+            }
+            consumed.extend(bindings.iter().filter_map(|(_, b)| match b {
+                button::Variant::Keyboard(key) => Some(*key),
+                _ => None,
+            }));
+        }
+        for action in self.actions.motion_entries.keys() {
+            visibility.entry(*action).or_insert(None);
+        }
+        visibility
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_stays_active_under_exclusive_layers() {
+        let mut im = InputManager::default();
+        im.push_context(InputContext::EDITOR, true);
+        im.push_context(InputContext::PHOTO, false);
+
+        assert!(im.is_context_active(InputContext::PHOTO));
+        assert!(im.is_context_active(InputContext::EDITOR));
+        assert!(!im.is_context_active(InputContext::GAMEPLAY));
+        assert!(im.is_context_active(InputContext::GLOBAL));
+    }
+
+    #[test]
+    fn removes_covered_layers() {
+        let mut im = InputManager::default();
+        im.push_context(InputContext::CINEMATIC, true);
+        im.push_context(InputContext::MENU, false);
+
+        assert!(im.remove_context(InputContext::CINEMATIC));
+        assert_eq!(im.top_context(), InputContext::MENU);
+        assert!(im.is_context_active(InputContext::GAMEPLAY));
+        assert!(!im.remove_context(InputContext::CINEMATIC));
+        assert!(!im.remove_context(InputContext::GLOBAL));
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::{
+    picking::pointer::{PointerId, PointerInteraction},
+    prelude::*,
+};
+
+use super::{Action, InputManager, InputMode};
+use crate::game_world::Ground;
+
+/**
+ * Mouse cursor on walkable ground, from the pointer hits of bevy_picking.
+ * Needs a picking backend (e.g. MeshPickingPlugin) for the cursor to ever be on the ground
+ */
+pub(super) fn update_cursor_world(
+    pointers: Query<(&PointerId, &PointerInteraction)>,
+    ground: Query<(), With<Ground>>,
+    mut input_manager: ResMut<InputManager>,
+) {
+    // hits are sorted nearest first, anything in front of the ground is looked through
+    input_manager.cursor_world = pointers
+        .iter()
+        .filter(|(id, _)| id.is_mouse())
+        .flat_map(|(_, interaction)| interaction.iter())
+        .find(|(entity, _)| ground.contains(*entity))
+        .and_then(|(_, hit)| hit.position);
+}
+
+impl InputManager {
+    pub fn register_action_cursor(&mut self, action: Action) {
+        self.cursor_actions.insert(action);
+    }
+
+    /// World point of the mouse cursor on Ground, None off the ground or in an inactive context
+    pub fn get_cursor_world(&self, action: Action) -> Option<Vec3> {
+        if !self.cursor_actions.contains(&action)
+            || !self.is_context_active(self.get_action_context(action))
+        {
+            return None;
+        }
+        if let Some(frame) = &self.replay_frame {
+            return frame.cursor;
+        }
+        self.cursor_world
+    }
+
+    /**
+     * Aiming uses the cursor action with mouse and keyboard,
+     * and the stick motion action with a gamepad
+     */
+    pub fn register_action_aim(&mut self, action: Action, cursor: Action, stick: Action) {
+        self.aim_entries.insert(action, (cursor, stick));
+    }
+
+    /// Horizontal direction to aim in from origin, yaw is the camera yaw the stick is relative to
+    pub fn get_aim_direction(&self, action: Action, origin: Vec3, yaw: f32) -> Option<Vec3> {
+        let (cursor, stick) = self.aim_entries.get(&action)?;
+        if !self.is_context_active(self.get_action_context(action)) {
+            return None;
+        }
+        match self.current_input_mode {
+            InputMode::MouseAndKeyboard => {
+                let to_cursor = self.get_cursor_world(*cursor)? - origin;
+                Vec3::new(to_cursor.x, 0., to_cursor.z).try_normalize()
+            }
+            InputMode::Gamepad | InputMode::Touch => self
+                .get_motion(*stick)
+                .get_motion_opt_y(yaw)
+                .and_then(Vec3::try_normalize),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+
+use super::{button, motion, Action, InputManager, InputMode, InputModeChanged, InputType};
+
+/**
+ * How to show the binding of an action to the player.
+ * name is for text ("K", "Y/North"), icon is an id to look up a glyph image by
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputPrompt {
+    pub name: String,
+    pub icon: String,
+}
+
+/**
+ * UI text showing the binding of an action for the current input mode.
+ * Every "{}" in template is replaced with the prompt name
+ */
+#[derive(Component, Debug, Clone)]
+#[require(Text)]
+pub struct ActionPrompt {
+    pub action: Action,
+    pub template: String,
+}
+
+impl ActionPrompt {
+    pub fn new(action: Action) -> Self {
+        Self {
+            action,
+            template: "{}".to_string(),
+        }
+    }
+
+    pub fn with_template(mut self, template: &str) -> Self {
+        self.template = template.to_string();
+        self
+    }
+
+    fn text(&self, input_manager: &InputManager, mode: InputMode) -> String {
+        let name = input_manager
+            .get_prompt_for_mode(self.action, mode)
+            .map(|prompt| prompt.name)
+            .unwrap_or_else(|| "?".to_string());
+        self.template.replace("{}", &name)
+    }
+}
+
+fn button_input_type(button: &button::Variant) -> InputType {
+    match button {
+        button::Variant::Keyboard(_) => InputType::Keyboard,
+        button::Variant::Mouse(_) => InputType::Mouse,
+        button::Variant::Gamepad(_) => InputType::Gamepad,
+        button::Variant::Touch(_) => InputType::Touch,
+    }
+}
+
+fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    ["Key", "Digit", "Arrow"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .map(str::to_string)
+        .unwrap_or(name)
+}
+
+fn button_prompt(button: &button::Variant) -> InputPrompt {
+    let (name, icon) = match button {
+        button::Variant::Keyboard(key) => {
+            let name = key_name(*key);
+            let icon = format!("key_{}", name.to_lowercase());
+            (name, icon)
+        }
+        button::Variant::Mouse(mouse) => match mouse {
+            MouseButton::Other(n) => (format!("Mouse {n}"), format!("mouse_{n}")),
+            _ => (
+                format!("{mouse:?} Mouse"),
+                format!("mouse_{}", format!("{mouse:?}").to_lowercase()),
+            ),
+        },
+        // xbox layout, same as InputMode::Gamepad assumes
+        button::Variant::Gamepad(gamepad) => {
+            let name = match gamepad {
+                GamepadButton::South => "A/South",
+                GamepadButton::East => "B/East",
+                GamepadButton::West => "X/West",
+                GamepadButton::North => "Y/North",
+                GamepadButton::LeftTrigger => "LB",
+                GamepadButton::LeftTrigger2 => "LT",
+                GamepadButton::RightTrigger => "RB",
+                GamepadButton::RightTrigger2 => "RT",
+                GamepadButton::Select => "View",
+                GamepadButton::Start => "Menu",
+                GamepadButton::Mode => "Xbox",
+                GamepadButton::LeftThumb => "LS",
+                GamepadButton::RightThumb => "RS",
+                GamepadButton::DPadUp => "D-Pad Up",
+                GamepadButton::DPadDown => "D-Pad Down",
+                GamepadButton::DPadLeft => "D-Pad Left",
+                GamepadButton::DPadRight => "D-Pad Right",
+                _ => "Gamepad",
+            };
+            let icon = format!("gamepad_{}", format!("{gamepad:?}").to_lowercase());
+            (name.to_string(), icon)
+        }
+        button::Variant::Touch(id) => (format!("Touch {id}"), format!("touch_button_{id}")),
+    };
+    InputPrompt { name, icon }
+}
+
+fn motion_prompt(entry: &motion::Entry) -> Option<InputPrompt> {
+    let first = entry.relations.first()?;
+    let prompt = match first {
+        motion::Relation::GamepadAxis(axis, _) => {
+            let (name, icon) = match axis {
+                GamepadAxis::LeftStickX | GamepadAxis::LeftStickY => {
+                    ("Left Stick", "gamepad_left_stick")
+                }
+                GamepadAxis::RightStickX | GamepadAxis::RightStickY => {
+                    ("Right Stick", "gamepad_right_stick")
+                }
+                GamepadAxis::LeftZ => ("LT", "gamepad_lefttrigger2"),
+                GamepadAxis::RightZ => ("RT", "gamepad_righttrigger2"),
+                GamepadAxis::Other(_) => ("Gamepad", "gamepad"),
+            };
+            InputPrompt {
+                name: name.to_string(),
+                icon: icon.to_string(),
+            }
+        }
+        motion::Relation::Mouse(_) => InputPrompt {
+            name: "Mouse".to_string(),
+            icon: "mouse_motion".to_string(),
+        },
+        motion::Relation::MouseWheel(_) => InputPrompt {
+            name: "Mouse Wheel".to_string(),
+            icon: "mouse_wheel".to_string(),
+        },
+        motion::Relation::TouchStick(id) => InputPrompt {
+            name: "Touch Stick".to_string(),
+            icon: format!("touch_stick_{id}"),
+        },
+        // every key of the entry, e.g. W/S/D/A
+        motion::Relation::KeyCode(..) => {
+            let keys = entry
+                .relations
+                .iter()
+                .filter_map(|relation| match relation {
+                    motion::Relation::KeyCode(key, _) => Some(key_name(*key)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            InputPrompt {
+                icon: format!("keys_{}", keys.join("").to_lowercase()),
+                name: keys.join("/"),
+            }
+        }
+    };
+    Some(prompt)
+}
+
+impl InputManager {
+    /// Prompt of the first bound button of the action usable in the given input mode
+    pub fn get_prompt_for_mode(&self, action: Action, mode: InputMode) -> Option<InputPrompt> {
+        if let Some(button) = self
+            .get_button_bindings(action)
+            .iter()
+            .find(|button| button_input_type(button).is_mode(mode))
+        {
+            return Some(button_prompt(button));
+        }
+        self.get_motion_bindings(action)
+            .iter()
+            .filter(|entry| entry.input_type.is_mode(mode))
+            .find_map(motion_prompt)
+    }
+
+    /// Prompt for the current input mode, None if the action has no binding in it
+    pub fn get_prompt(&self, action: Action) -> Option<InputPrompt> {
+        self.get_prompt_for_mode(action, self.current_input_mode)
+    }
+}
+
+pub(super) fn update_prompts_on_mode_change(
+    trigger: Trigger<InputModeChanged>,
+    input_manager: Res<InputManager>,
+    mut prompts: Query<(&ActionPrompt, &mut Text)>,
+) {
+    let mode = trigger.event().0;
+    for (prompt, mut text) in prompts.iter_mut() {
+        text.0 = prompt.text(&input_manager, mode);
+    }
+}
+
+// new or changed prompts, and every prompt after bindings changed
+pub(super) fn update_changed_prompts(
+    input_manager: Res<InputManager>,
+    mut prompts: Query<(Ref<ActionPrompt>, &mut Text)>,
+    mut bindings_generation: Local<u32>,
+) {
+    let rebound = *bindings_generation != input_manager.bindings_generation;
+    *bindings_generation = input_manager.bindings_generation;
+    for (prompt, mut text) in prompts.iter_mut() {
+        if rebound || prompt.is_changed() {
+            text.0 = prompt.text(&input_manager, input_manager.current_input_mode);
+        }
+    }
+}
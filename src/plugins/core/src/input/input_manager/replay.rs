@@ -0,0 +1,338 @@
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use super::{Action, InputManager};
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse(usize, ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "replay file: {err}"),
+            Self::Parse(frame, err) => write!(f, "replay file, frame {frame}: {err}"),
+            Self::Serialize(err) => write!(f, "replay file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ButtonFrame {
+    pub pressed: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+    pub long_pressed: bool,
+    pub tapped: bool,
+    pub double_tapped: bool,
+    pub held_for: Option<Duration>,
+}
+
+/**
+ * Action state of a single frame, as seen through the InputManager queries.
+ * Only actions that are active are stored, everything else is released/zero
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InputFrame {
+    pub delta: Duration,
+    pub buttons: BTreeMap<String, ButtonFrame>,
+    pub motions: BTreeMap<String, Vec2>,
+    #[serde(default)]
+    pub cursor: Option<Vec3>,
+}
+
+impl InputFrame {
+    fn button(&self, action: Action) -> ButtonFrame {
+        self.buttons.get(action.0).copied().unwrap_or_default()
+    }
+
+    fn motion(&self, action: Action) -> Vec2 {
+        self.motions.get(action.0).copied().unwrap_or_default()
+    }
+}
+
+/// Appends one line per frame to the file, so a crash still leaves a usable recording
+#[derive(Resource)]
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    fn write_frame(&mut self, frame: &InputFrame) -> Result<(), ReplayError> {
+        let line = ron::to_string(frame).map_err(ReplayError::Serialize)?;
+        writeln!(self.writer, "{line}").map_err(ReplayError::Io)?;
+        self.writer.flush().map_err(ReplayError::Io)
+    }
+}
+
+/**
+ * Feeds recorded frames to the InputManager queries instead of real devices.
+ * Time is stepped with the recorded frame deltas, so systems see the same Time as the recording
+ */
+#[derive(Resource)]
+pub struct InputReplay {
+    frames: Vec<InputFrame>,
+    next_frame: usize,
+    pub exit_on_finish: bool,
+}
+
+impl InputReplay {
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let contents = fs::read_to_string(path).map_err(ReplayError::Io)?;
+        let frames = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| {
+                ron::from_str::<InputFrame>(line).map_err(|e| ReplayError::Parse(i, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            frames,
+            next_frame: 0,
+            exit_on_finish: false,
+        })
+    }
+
+    pub fn with_exit_on_finish(mut self) -> Self {
+        self.exit_on_finish = true;
+        self
+    }
+}
+
+#[derive(Debug, Event)]
+pub struct InputReplayFinished;
+
+impl InputManager {
+    fn record_frame(&self, delta: Duration) -> InputFrame {
+        let buttons = self
+            .actions
+            .button_entries
+            .iter()
+            .map(|(action, entry)| {
+                let frame = ButtonFrame {
+                    pressed: self.actions.is_action_pressed(*action),
+                    just_pressed: self.actions.is_action_just_pressed(*action),
+                    just_released: self.actions.is_action_just_released(*action),
+                    long_pressed: entry.timing.long_pressed,
+                    tapped: entry.timing.tapped,
+                    double_tapped: entry.timing.double_tapped,
+                    held_for: entry.held_duration(),
+                };
+                (action.0.to_string(), frame)
+            })
+            .filter(|(_, frame)| *frame != ButtonFrame::default())
+            .collect();
+        let motions = self
+            .actions
+            .motion_entries
+            .iter()
+            .map(|(action, entry)| (action.0.to_string(), entry.output.raw_vec2()))
+            .filter(|(_, motion)| *motion != Vec2::ZERO)
+            .collect();
+
+        InputFrame {
+            delta,
+            buttons,
+            motions,
+            cursor: self.cursor_world,
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay_frame.is_some()
+    }
+
+    pub(super) fn replayed_button(&self, action: Action) -> Option<ButtonFrame> {
+        self.replay_frame.as_ref().map(|frame| frame.button(action))
+    }
+
+    pub(super) fn replayed_motion(&self, action: Action) -> Option<Vec2> {
+        self.replay_frame.as_ref().map(|frame| frame.motion(action))
+    }
+}
+
+pub(super) fn record_input(
+    mut recorder: ResMut<InputRecorder>,
+    input_manager: Res<InputManager>,
+    time: Res<Time<Real>>,
+    mut commands: Commands,
+) {
+    let frame = input_manager.record_frame(time.delta());
+    if let Err(err) = recorder.write_frame(&frame) {
+        // frames already on disk are still a valid recording
+        error!("input recording stopped: {err}");
+        commands.remove_resource::<InputRecorder>();
+    }
+}
+
+pub(super) fn start_replay(replay: Res<InputReplay>, mut strategy: ResMut<TimeUpdateStrategy>) {
+    if let Some(first) = replay.frames.first() {
+        *strategy = TimeUpdateStrategy::ManualDuration(first.delta);
+    }
+}
+
+pub(super) fn replay_input(
+    mut replay: ResMut<InputReplay>,
+    mut input_manager: ResMut<InputManager>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut exit: EventWriter<AppExit>,
+    mut commands: Commands,
+) {
+    let Some(frame) = replay.frames.get(replay.next_frame).cloned() else {
+        input_manager.replay_frame = None;
+        *strategy = TimeUpdateStrategy::Automatic;
+        commands.remove_resource::<InputReplay>();
+        commands.trigger(InputReplayFinished);
+        if replay.exit_on_finish {
+            exit.send(AppExit::Success);
+        }
+        return;
+    };
+
+    input_manager.replay_frame = Some(frame);
+    replay.next_frame += 1;
+
+    // First of the next frame updates Time with this
+    if let Some(next) = replay.frames.get(replay.next_frame) {
+        *strategy = TimeUpdateStrategy::ManualDuration(next.delta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::InputPlugin;
+
+    use super::*;
+    use crate::input::input_manager::{button, InputManagerPlugin};
+
+    static JUMP: Action = Action("jump");
+    static WALK: Action = Action("walk");
+
+    #[derive(Resource, Default)]
+    struct Finished(bool);
+
+    fn frame(delta_ms: u64, jump: Option<ButtonFrame>, walk: Option<Vec2>) -> InputFrame {
+        InputFrame {
+            delta: Duration::from_millis(delta_ms),
+            buttons: jump
+                .map(|jump| (JUMP.0.to_string(), jump))
+                .into_iter()
+                .collect(),
+            motions: walk
+                .map(|walk| (WALK.0.to_string(), walk))
+                .into_iter()
+                .collect(),
+            cursor: None,
+        }
+    }
+
+    #[test]
+    fn replays_headless() {
+        let frames = [
+            frame(
+                16,
+                Some(ButtonFrame {
+                    pressed: true,
+                    just_pressed: true,
+                    ..default()
+                }),
+                None,
+            ),
+            frame(
+                20,
+                Some(ButtonFrame {
+                    pressed: true,
+                    ..default()
+                }),
+                Some(Vec2::X),
+            ),
+            frame(
+                25,
+                Some(ButtonFrame {
+                    just_released: true,
+                    ..default()
+                }),
+                None,
+            ),
+        ];
+        let path = std::env::temp_dir().join(format!("replay-test-{}.ron", std::process::id()));
+        let mut recording = frames
+            .iter()
+            .map(|frame| ron::to_string(frame).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        recording.push('\n');
+        fs::write(&path, recording).unwrap();
+        let replay = InputReplay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin, InputManagerPlugin))
+            .insert_resource(replay)
+            .init_resource::<Finished>()
+            .add_observer(
+                |_: Trigger<InputReplayFinished>, mut finished: ResMut<Finished>| {
+                    finished.0 = true;
+                },
+            );
+        {
+            let mut im = app.world_mut().resource_mut::<InputManager>();
+            im.register_action_button(JUMP, vec![button::Variant::Keyboard(KeyCode::Space)]);
+            im.register_action_motion(WALK, vec![]);
+        }
+
+        app.update();
+        let im = app.world().resource::<InputManager>();
+        assert!(im.is_replaying());
+        assert!(im.is_action_just_pressed(JUMP));
+        assert_eq!(im.get_motion(WALK).raw_vec2(), Vec2::ZERO);
+
+        app.update();
+        let im = app.world().resource::<InputManager>();
+        assert!(im.is_action_pressed(JUMP));
+        assert!(!im.is_action_just_pressed(JUMP));
+        assert_eq!(im.get_motion(WALK).raw_vec2(), Vec2::X);
+        // time steps with the recorded delta of the frame
+        assert_eq!(
+            app.world().resource::<Time<Real>>().delta(),
+            frames[1].delta
+        );
+
+        app.update();
+        let im = app.world().resource::<InputManager>();
+        assert!(im.is_action_just_released(JUMP));
+        assert!(!im.is_action_pressed(JUMP));
+        assert_eq!(
+            app.world().resource::<Time<Real>>().delta(),
+            frames[2].delta
+        );
+
+        app.update();
+        assert!(app.world().resource::<Finished>().0);
+        assert!(!app.world().resource::<InputManager>().is_replaying());
+        assert!(!app.world().contains_resource::<InputReplay>());
+    }
+}
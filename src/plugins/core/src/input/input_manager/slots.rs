@@ -0,0 +1,73 @@
+use bevy::{
+    input::gamepad::{GamepadConnection, GamepadEvent},
+    prelude::*,
+};
+
+use super::InputManager;
+
+/**
+ * Source of input for one local player.
+ * Keyboard and mouse always share a slot, as do the touch controls,
+ * every connected gamepad gets its own
+ */
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum PlayerSlot {
+    KeyboardMouse,
+    Touch,
+    Gamepad(Entity),
+}
+
+#[derive(Debug, Clone, Copy, Event)]
+pub struct PlayerSlotConnected(pub PlayerSlot);
+
+#[derive(Debug, Clone, Copy, Event)]
+pub struct PlayerSlotDisconnected(pub PlayerSlot);
+
+impl InputManager {
+    // new slots start with the bindings of every registered action
+    fn add_slot(&mut self, slot: PlayerSlot) -> bool {
+        if self.slots.contains_key(&slot) {
+            return false;
+        }
+        self.slots.insert(slot, self.actions.bindings_copy());
+        true
+    }
+
+    fn remove_slot(&mut self, slot: PlayerSlot) -> bool {
+        self.slots.remove(&slot).is_some()
+    }
+
+    // after rebinding, slots pick up the new bindings, unchanged actions keep their state
+    pub(super) fn sync_slots(&mut self) {
+        for input in self.slots.values_mut() {
+            input.sync_bindings(&self.actions);
+        }
+        self.bindings_generation = self.bindings_generation.wrapping_add(1);
+    }
+}
+
+pub(super) fn update_player_slots(
+    mut gamepad: EventReader<GamepadEvent>,
+    mut input_manager: ResMut<InputManager>,
+    mut commands: Commands,
+) {
+    for event in gamepad.read() {
+        let GamepadEvent::Connection(event) = event else {
+            continue;
+        };
+
+        let slot = PlayerSlot::Gamepad(event.gamepad);
+        match event.connection {
+            GamepadConnection::Connected { .. } => {
+                if input_manager.add_slot(slot) {
+                    commands.trigger(PlayerSlotConnected(slot));
+                }
+            }
+            GamepadConnection::Disconnected => {
+                if input_manager.remove_slot(slot) {
+                    commands.trigger(PlayerSlotDisconnected(slot));
+                }
+            }
+        }
+    }
+}
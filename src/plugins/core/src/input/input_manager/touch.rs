@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::{contexts::InputContext, InputManager, InputMode};
+
+/**
+ * On-screen stick, drives motion actions bound with Relation::TouchStick(id).
+ * A touch that starts inside the node keeps steering it until released,
+ * the stick is fully deflected at radius logical pixels from the node center
+ */
+#[derive(Component)]
+#[require(TouchControl)]
+pub struct VirtualStick {
+    pub id: u8,
+    pub radius: f32,
+}
+
+/// Child node of a VirtualStick that follows the deflection
+#[derive(Component)]
+#[require(Node)]
+pub struct VirtualStickKnob;
+
+/// On-screen button, drives button actions bound with Variant::Touch(id)
+#[derive(Component)]
+#[require(TouchControl)]
+pub struct TouchButton(pub u8);
+
+/// Touch controls are only shown in InputMode::Touch
+#[derive(Component, Default)]
+#[require(Node, Visibility(hidden))]
+pub struct TouchControl;
+
+fn hidden() -> Visibility {
+    Visibility::Hidden
+}
+
+// logical pixel rect of a ui node, touches are in logical pixels
+fn node_rect(node: &ComputedNode, transform: &GlobalTransform) -> Rect {
+    Rect::from_center_size(
+        transform.translation().truncate() * node.inverse_scale_factor(),
+        node.size() * node.inverse_scale_factor(),
+    )
+}
+
+pub(super) fn read_touch_controls(
+    touches: Res<Touches>,
+    sticks: Query<(Entity, &VirtualStick, &ComputedNode, &GlobalTransform)>,
+    buttons: Query<(Entity, &TouchButton, &ComputedNode, &GlobalTransform)>,
+    // touch id -> control it started on
+    mut captures: Local<HashMap<u64, Entity>>,
+    mut input_manager: ResMut<InputManager>,
+) {
+    for touch in touches.iter_just_pressed() {
+        let position = touch.position();
+        if let Some((entity, ..)) = sticks
+            .iter()
+            .find(|(_, _, node, transform)| node_rect(node, transform).contains(position))
+        {
+            captures.insert(touch.id(), entity);
+        } else if let Some((entity, button, ..)) = buttons
+            .iter()
+            .find(|(_, _, node, transform)| node_rect(node, transform).contains(position))
+        {
+            captures.insert(touch.id(), entity);
+            input_manager.touch_button_events.push((button.0, true));
+        }
+    }
+
+    for touch in touches
+        .iter_just_released()
+        .chain(touches.iter_just_canceled())
+    {
+        let Some(entity) = captures.remove(&touch.id()) else {
+            continue;
+        };
+        if let Ok((_, button, ..)) = buttons.get(entity) {
+            input_manager.touch_button_events.push((button.0, false));
+        }
+    }
+
+    input_manager.touch_sticks.clear();
+    for (entity, stick, node, transform) in sticks.iter() {
+        let center = node_rect(node, transform).center();
+        let value = captures
+            .iter()
+            .filter(|(_, captured)| **captured == entity)
+            .find_map(|(id, _)| touches.get_pressed(*id))
+            .map(|touch| {
+                // screen y points down, stick y points up like a gamepad stick
+                let offset = (touch.position() - center) / stick.radius.max(f32::EPSILON);
+                Vec2::new(offset.x, -offset.y).clamp_length_max(1.)
+            })
+            .unwrap_or_default();
+        input_manager.touch_sticks.insert(stick.id, value);
+    }
+}
+
+pub(super) fn update_touch_controls(
+    input_manager: Res<InputManager>,
+    mut controls: Query<&mut Visibility, With<TouchControl>>,
+    sticks: Query<(&VirtualStick, &ComputedNode, &Children)>,
+    mut knobs: Query<(&mut Node, &ComputedNode), With<VirtualStickKnob>>,
+) {
+    // touch controls are gameplay controls, hidden in the editor, cutscenes and photo mode
+    let visibility = match input_manager.current_input_mode {
+        InputMode::Touch if input_manager.is_context_active(InputContext::GAMEPLAY) => {
+            Visibility::Inherited
+        }
+        _ => Visibility::Hidden,
+    };
+    for mut control in controls.iter_mut() {
+        control.set_if_neq(visibility);
+    }
+
+    for (stick, stick_node, children) in sticks.iter() {
+        let center = stick_node.size() * stick_node.inverse_scale_factor() / 2.;
+        let value = input_manager
+            .touch_sticks
+            .get(&stick.id)
+            .copied()
+            .unwrap_or_default();
+        let mut knobs = knobs.iter_many_mut(children);
+        while let Some((mut node, computed)) = knobs.fetch_next() {
+            // knob is absolutely positioned with its center on the deflection
+            let half_size = computed.size() * computed.inverse_scale_factor() / 2.;
+            node.position_type = PositionType::Absolute;
+            node.left = Val::Px(center.x + value.x * stick.radius - half_size.x);
+            node.top = Val::Px(center.y - value.y * stick.radius - half_size.y);
+        }
+    }
+}
@@ -17,6 +17,7 @@ impl bevy::prelude::Plugin for CorePlugin {
             input::input_manager::InputManagerPlugin,
             camera::isometric_camera::IsometricCameraPlugin,
             player::PlayerPlugin,
+            game_world::GameWorldPlugin,
             enemies::EnemiesPlugin,
             settings::plugins::VendorPlugin,
             PhysicsPlugins::default(), // avian3d
@@ -0,0 +1,242 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+// capsule standing on the transform origin, the origin stays at the feet
+const CAPSULE_RADIUS: f32 = 0.4;
+const CAPSULE_LENGTH: f32 = 1.;
+const CAPSULE_CENTER: Vec3 = Vec3::new(0., CAPSULE_RADIUS + CAPSULE_LENGTH / 2., 0.);
+// gap kept to surfaces, so the next cast does not start inside them
+const SKIN_WIDTH: f32 = 0.02;
+// degrees, anything steeper is a wall
+const MAX_SLOPE: f32 = 45.;
+const STEP_HEIGHT: f32 = 0.3;
+// distance below the feet the character sticks to the ground from, e.g. walking down a slope
+const GROUND_SNAP: f32 = 0.15;
+const GRAVITY: f32 = 20.;
+const TERMINAL_VELOCITY: f32 = 30.;
+// surfaces the move can slide along in one frame, e.g. into a corner
+const MAX_SLIDES: usize = 4;
+
+/**
+ * Kinematic body moved by collide-and-slide against static geometry.
+ * States set desired_velocity every frame they want to move, it is cleared after each move,
+ * gravity, ground detection, slopes and steps are handled here
+ */
+#[derive(Component, Debug)]
+#[require(RigidBody(|| RigidBody::Kinematic), Collider(character_collider))]
+pub struct CharacterController {
+    // horizontal world units per second, the vertical part is left to gravity
+    pub desired_velocity: Vec3,
+    pub gravity_scale: f32,
+    velocity: Vec3,
+    grounded: bool,
+    ground_normal: Vec3,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            desired_velocity: Vec3::ZERO,
+            gravity_scale: 1.,
+            velocity: Vec3::ZERO,
+            grounded: false,
+            ground_normal: Vec3::Y,
+        }
+    }
+}
+
+impl CharacterController {
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    pub fn ground_normal(&self) -> Option<Vec3> {
+        self.grounded.then_some(self.ground_normal)
+    }
+
+    /// Overrides the vertical velocity, upwards lifts the character off the ground
+    pub fn set_vertical_velocity(&mut self, vertical: f32) {
+        self.velocity.y = vertical;
+        if vertical > 0. {
+            self.grounded = false;
+        }
+    }
+}
+
+fn character_collider() -> Collider {
+    Collider::compound(vec![(
+        CAPSULE_CENTER,
+        Quat::IDENTITY,
+        Collider::capsule(CAPSULE_RADIUS, CAPSULE_LENGTH),
+    )])
+}
+
+fn is_walkable(normal: Vec3) -> bool {
+    normal.y >= MAX_SLOPE.to_radians().cos()
+}
+
+struct Caster<'a, 'w, 's> {
+    spatial_query: &'a SpatialQuery<'w, 's>,
+    filter: SpatialQueryFilter,
+    blocking: &'a dyn Fn(Entity) -> bool,
+    shape: Collider,
+}
+
+impl Caster<'_, '_, '_> {
+    // hit of the capsule with its feet at origin, moved by movement
+    fn cast(&self, origin: Vec3, movement: Vec3) -> Option<ShapeHitData> {
+        let (direction, distance) = Dir3::new_and_length(movement).ok()?;
+        let config = ShapeCastConfig {
+            max_distance: distance + SKIN_WIDTH,
+            ignore_origin_penetration: true,
+            ..default()
+        };
+        self.spatial_query.cast_shape_predicate(
+            &self.shape,
+            origin + CAPSULE_CENTER,
+            Quat::IDENTITY,
+            direction,
+            &config,
+            &self.filter,
+            self.blocking,
+        )
+    }
+
+    /**
+     * Moves from origin by movement, sliding along whatever is in the way.
+     * Returns the end position and the velocity with the parts into surfaces removed
+     */
+    fn slide(
+        &self,
+        mut position: Vec3,
+        movement: Vec3,
+        mut velocity: Vec3,
+        grounded: bool,
+    ) -> (Vec3, Vec3) {
+        let mut remaining = movement;
+        for _ in 0..MAX_SLIDES {
+            let Ok((direction, distance)) = Dir3::new_and_length(remaining) else {
+                break;
+            };
+            let Some(hit) = self.cast(position, remaining) else {
+                position += remaining;
+                break;
+            };
+            let travel = (hit.distance - SKIN_WIDTH).clamp(0., distance);
+            position += direction * travel;
+            remaining = direction * (distance - travel);
+
+            let mut normal = hit.normal1;
+            if !is_walkable(normal) {
+                // a step is climbed instead of slid along
+                if grounded {
+                    let horizontal = Vec3::new(remaining.x, 0., remaining.z);
+                    if let Some(stepped) = self.step_up(position, horizontal) {
+                        position = stepped;
+                        remaining.x = 0.;
+                        remaining.z = 0.;
+                        continue;
+                    }
+                }
+                // walls and steep slopes only push sideways, the character can't walk up them
+                if let Ok(flat) = Dir3::new(Vec3::new(normal.x, 0., normal.z)) {
+                    if grounded || remaining.y >= 0. {
+                        normal = flat.as_vec3();
+                    }
+                }
+            }
+            remaining -= normal * remaining.dot(normal).min(0.);
+            velocity -= normal * velocity.dot(normal).min(0.);
+        }
+        (position, velocity)
+    }
+
+    // up, across and back down onto walkable ground, None if something is in the way
+    fn step_up(&self, position: Vec3, horizontal: Vec3) -> Option<Vec3> {
+        if horizontal.length_squared() < f32::EPSILON {
+            return None;
+        }
+        let up = Vec3::Y * STEP_HEIGHT;
+        if self.cast(position, up).is_some() {
+            return None;
+        }
+        // at least a capsule radius across, so the feet end up on the step
+        let across = horizontal.normalize() * horizontal.length().max(CAPSULE_RADIUS);
+        if self.cast(position + up, across).is_some() {
+            return None;
+        }
+        let top = position + up + across;
+        let hit = self.cast(top, -up)?;
+        if !is_walkable(hit.normal1) {
+            return None;
+        }
+        Some(top - Vec3::Y * (hit.distance - SKIN_WIDTH).max(0.))
+    }
+}
+
+pub(super) fn move_characters(
+    spatial_query: SpatialQuery,
+    bodies: Query<&RigidBody>,
+    sensors: Query<(), With<Sensor>>,
+    mut characters: Query<(Entity, &mut CharacterController, &mut Transform)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0. {
+        return;
+    }
+    // only static geometry blocks, dynamic bodies are pushed by the physics step instead
+    let blocking = |entity: Entity| {
+        !sensors.contains(entity) && bodies.get(entity).is_ok_and(|body| body.is_static())
+    };
+
+    for (entity, mut controller, mut transform) in characters.iter_mut() {
+        let caster = Caster {
+            spatial_query: &spatial_query,
+            filter: SpatialQueryFilter::from_excluded_entities([entity]),
+            blocking: &blocking,
+            shape: Collider::capsule(CAPSULE_RADIUS, CAPSULE_LENGTH),
+        };
+
+        let mut velocity = Vec3::new(
+            controller.desired_velocity.x,
+            controller.velocity.y,
+            controller.desired_velocity.z,
+        );
+        if controller.grounded && velocity.y <= 0. {
+            // walking on a slope follows its surface instead of bumping down it
+            velocity = velocity.reject_from(controller.ground_normal);
+        } else {
+            velocity.y =
+                (velocity.y - GRAVITY * controller.gravity_scale * dt).max(-TERMINAL_VELOCITY);
+        }
+
+        let (mut position, velocity) = caster.slide(
+            transform.translation,
+            velocity * dt,
+            velocity,
+            controller.grounded,
+        );
+
+        // ground check, snapping down to it unless moving up
+        let ground = caster
+            .cast(position, Vec3::NEG_Y * GROUND_SNAP)
+            .filter(|hit| is_walkable(hit.normal1));
+        controller.grounded = ground.is_some() && velocity.y <= 0.;
+        if let Some(hit) = ground.filter(|_| controller.grounded) {
+            position.y -= (hit.distance - SKIN_WIDTH).max(0.);
+            controller.ground_normal = hit.normal1;
+            controller.velocity = Vec3::new(velocity.x, 0., velocity.z);
+        } else {
+            controller.ground_normal = Vec3::Y;
+            controller.velocity = velocity;
+        }
+
+        transform.translation = position;
+        controller.desired_velocity = Vec3::ZERO;
+    }
+}
@@ -1,28 +1,36 @@
 use bevy::prelude::*;
+use std::time::Duration;
 
 use crate::{
-    camera::isometric_camera::CameraYaw,
+    camera::isometric_camera::{follow::CameraTarget, CameraYaw},
     input::input_manager::{self, button, motion, InputManager},
+    utils::fsm::StateMachine,
 };
 
-use super::states;
+use super::{
+    character_controller::CharacterController,
+    states::{floaty::FloatyHat, PlayerState},
+};
 
-enum PlayerState {
+enum PlayerLife {
     NotSpawned,
     Dead,
     Alive,
 }
 
 #[derive(Component)]
-#[require(Transform(|| Transform::from_xyz(0., 0., 0.)))]
+#[require(
+    Transform(|| Transform::from_xyz(0., 0., 0.)),
+    CameraTarget,
+    CharacterController,
+    FloatyHat,
+    StateMachine<PlayerState>(|| StateMachine::new(PlayerState::IdleRun))
+)]
 pub struct Player {
-    state: PlayerState,
+    state: PlayerLife,
 }
 
-#[derive(Component)]
-pub struct PlayerFsm;
-
-#[derive(Event)]
+#[derive(Event, Clone)]
 pub struct PlayerMovementEvent {
     pub motion: Option<Vec3>,
 }
@@ -32,17 +40,17 @@ impl PlayerMovementEvent {
     }
 }
 
-#[derive(Event)]
+#[derive(Event, Clone)]
 pub struct PlayerFloatyEvent {
     pub active: bool,
 }
 
-#[derive(Event)]
+#[derive(Event, Clone)]
 pub struct PlayerCordyCeptEvent {
     pub active: bool,
 }
 
-#[derive(Event)]
+#[derive(Event, Clone)]
 pub enum PlayerEvent {
     Movement(PlayerMovementEvent),
     Floaty(PlayerFloatyEvent),
@@ -55,8 +63,17 @@ pub struct PlayerSpawn {
 }
 
 static MOVEMENT: input_manager::Action = input_manager::Action("movement");
-static ABILITY_FLOATY: input_manager::Action = input_manager::Action("ability_floaty");
-static ABILITY_CORDYCEPT: input_manager::Action = input_manager::Action("ability_cordycept");
+pub(super) static ABILITY_FLOATY: input_manager::Action = input_manager::Action("ability_floaty");
+pub(super) static ABILITY_CORDYCEPT: input_manager::Action =
+    input_manager::Action("ability_cordycept");
+
+// ability presses landing mid state change are picked up by the next state within this
+const ABILITY_BUFFER: Duration = Duration::from_millis(150);
+
+// ids of the on-screen touch controls the player actions are bound to
+pub const TOUCH_STICK_MOVEMENT: u8 = 0;
+pub const TOUCH_BUTTON_FLOATY: u8 = 0;
+pub const TOUCH_BUTTON_CORDYCEPT: u8 = 1;
 
 pub(super) fn register_input(mut im: ResMut<input_manager::InputManager>) {
     im.register_action_motion(
@@ -78,6 +95,10 @@ pub(super) fn register_input(mut im: ResMut<input_manager::InputManager>) {
                     motion::Relation::GamepadAxis(GamepadAxis::LeftStickX, motion::Axis::X),
                 ],
             },
+            motion::Entry {
+                input_type: input_manager::InputType::Touch,
+                relations: vec![motion::Relation::TouchStick(TOUCH_STICK_MOVEMENT)],
+            },
         ],
     );
 
@@ -86,6 +107,7 @@ pub(super) fn register_input(mut im: ResMut<input_manager::InputManager>) {
         vec![
             button::Variant::Keyboard(KeyCode::KeyK),
             button::Variant::Gamepad(GamepadButton::North),
+            button::Variant::Touch(TOUCH_BUTTON_FLOATY),
         ],
     );
     im.register_action_button(
@@ -93,8 +115,11 @@ pub(super) fn register_input(mut im: ResMut<input_manager::InputManager>) {
         vec![
             button::Variant::Keyboard(KeyCode::KeyJ),
             button::Variant::Gamepad(GamepadButton::East),
+            button::Variant::Touch(TOUCH_BUTTON_CORDYCEPT),
         ],
     );
+    im.set_action_buffer(ABILITY_FLOATY, ABILITY_BUFFER);
+    im.set_action_buffer(ABILITY_CORDYCEPT, ABILITY_BUFFER);
 }
 
 pub(super) fn spawn_player(
@@ -107,18 +132,12 @@ pub(super) fn spawn_player(
         unreachable!("Trying to spawn a new player entity. STOP!");
     }
 
-    // Spawning of Player Fsm, use new_state! after this
-    let state = commands.add_observer(states::idle_run::process_event).id();
-    let fsm_entity = commands.spawn(PlayerFsm).insert_children(0, &[state]).id();
-
-    commands
-        .spawn((
-            Player {
-                state: PlayerState::Alive,
-            },
-            player_spawn.transform,
-        ))
-        .insert_children(0, &[fsm_entity]);
+    commands.spawn((
+        Player {
+            state: PlayerLife::Alive,
+        },
+        player_spawn.transform,
+    ));
 }
 
 pub(super) fn process_input(
@@ -4,12 +4,16 @@
  * PlayerController as input interpreter and main entry point to the module, which
  * then sends Trigger<_,_> that the other audio/visual systems will add_observer for.
  */
+pub mod character_controller;
 pub mod controller;
 pub mod visuals;
 pub mod states;
 
 use bevy::prelude::*;
 
+use crate::utils::fsm::FsmAppExt;
+use states::PlayerState;
+
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
@@ -27,16 +31,38 @@ impl Plugin for PlayerControllerPlugin {
                 //
             ),
         )
-        .add_systems(Update, controller::process_input)
+        // states set the desired velocity from the input events, the character moves after
+        .add_systems(
+            Update,
+            (
+                controller::process_input,
+                states::floaty::update_ballooning,
+                character_controller::move_characters,
+            )
+                .chain(),
+        )
         .add_observer(controller::spawn_player);
+
+        app.add_state_enter(PlayerState::IdleRun, states::idle_run::on_enter)
+            .add_state_event(PlayerState::IdleRun, states::idle_run::on_event)
+            .add_state_enter(PlayerState::Floaty, states::floaty::on_enter)
+            .add_state_exit(PlayerState::Floaty, states::floaty::on_exit)
+            .add_state_event(PlayerState::Floaty, states::floaty::on_event)
+            .add_state_enter(PlayerState::CordyCept, states::cordycept::on_enter)
+            .add_state_event(PlayerState::CordyCept, states::cordycept::on_event);
     }
 }
 
 struct RenderPlugin;
 impl Plugin for RenderPlugin {
     fn build(&self, app: &mut App) {
-        app.add_observer(visuals::spawn_player_mesh)
+        app.init_resource::<visuals::StateAnimations>()
+            .add_systems(
+                Update,
+                visuals::update_state_blend.after(character_controller::move_characters),
+            )
+            .add_observer(visuals::spawn_player_mesh)
             .add_observer(visuals::setup_once_loaded)
-            .add_observer(visuals::observe_player_event);
+            .add_observer(visuals::observe_state_changed);
     }
 }
@@ -1,43 +1,55 @@
 use crate::{
-    new_state,
-    player::controller::{Player, PlayerEvent, PlayerFsm},
+    input::input_manager::InputManager,
+    player::{
+        character_controller::CharacterController,
+        controller::{Player, PlayerEvent, ABILITY_CORDYCEPT},
+    },
+    utils::fsm::{FsmCommandsExt, StateEvent},
 };
 use bevy::prelude::*;
 
-use super::utils::movement;
+use super::{utils::movement, PlayerState};
 
 const RUN_SPEED: f32 = 7.0;
 const ROTATION_SPEED: f32 = 15.0;
 
-pub fn process_event(
-    event: Trigger<PlayerEvent>,
+pub fn on_event(
+    In(event): In<StateEvent<PlayerEvent>>,
     mut commands: Commands,
-    fsm: Single<Entity, With<PlayerFsm>>,
-    current_state: Single<&Children, With<PlayerFsm>>,
-    mut transform: Single<&mut Transform, With<Player>>,
+    mut players: Query<(&mut Transform, &mut CharacterController), With<Player>>,
     time: Res<Time>,
 ) {
-    match event.event() {
-        PlayerEvent::CordyCept(event) => {
-            if !event.active {
-                new_state!(commands, fsm, current_state, super::idle_run::process_event);
+    let Ok((mut transform, mut controller)) = players.get_mut(event.entity) else {
+        return;
+    };
+    match event.event {
+        PlayerEvent::CordyCept(cordycept) => {
+            if !cordycept.active {
+                commands.set_state(event.entity, PlayerState::IdleRun);
             }
         }
-        PlayerEvent::Movement(event) => {
+        PlayerEvent::Movement(movement) => {
             // get entities?
             // ability might just be global, as long as someone has the status
-            if let Some(motion) = event.motion {
-                let movement = motion * RUN_SPEED * time.delta_secs();
-                transform.translation += movement;
-                movement::rotate_player(motion, &mut *transform, ROTATION_SPEED, &time);
+            if let Some(motion) = movement.motion {
+                controller.desired_velocity = motion * RUN_SPEED;
+                movement::rotate_player(motion, &mut transform, ROTATION_SPEED, &time);
 
-                commands.trigger(CordyCeptMovement(movement));
+                // ants follow the intended movement, walls only hold back the player
+                commands.trigger(CordyCeptMovement(
+                    controller.desired_velocity * time.delta_secs(),
+                ));
             }
         }
         _ => (),
     }
 }
 
+/// Uses up the press that got here, only now that the transition went through
+pub fn on_enter(_: In<Entity>, mut im: ResMut<InputManager>) {
+    im.consume_buffered_press(ABILITY_CORDYCEPT);
+}
+
 #[derive(Component)]
 pub struct CordyCeptedComponent;
 #[derive(Event)]
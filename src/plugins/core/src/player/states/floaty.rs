@@ -1,21 +1,153 @@
+use bevy::prelude::*;
+
 use crate::{
-    new_state,
-    player::controller::{PlayerEvent, PlayerFsm},
+    game_world::wind::Wind,
+    input::input_manager::InputManager,
+    player::{
+        character_controller::CharacterController,
+        controller::{Player, PlayerEvent, ABILITY_FLOATY},
+    },
+    utils::fsm::{FsmCommandsExt, StateEvent},
 };
-use bevy::prelude::*;
 
-pub fn process_event(
-    event: Trigger<PlayerEvent>,
-    fsm: Single<Entity, With<PlayerFsm>>,
-    current_state: Single<&Children, With<PlayerFsm>>,
+use super::{utils::movement, PlayerState};
+
+// seconds of lift in a full hat
+const AIR_CAPACITY: f32 = 1.5;
+// air per second, only on the ground with the hat deflated
+const AIR_REFILL: f32 = 1.;
+// upward acceleration while the hat has air
+const LIFT_ACCELERATION: f32 = 25.;
+const MAX_RISE_SPEED: f32 = 4.;
+// the inflated hat slows the fall down to this, also once the air is used up
+const MAX_FALL_SPEED: f32 = 1.5;
+const GRAVITY_SCALE: f32 = 0.3;
+// horizontal control in the air, reached with AIR_ACCELERATION so the player drifts
+const AIR_SPEED: f32 = 4.;
+const AIR_ACCELERATION: f32 = 6.;
+// how much of the wind the hat catches
+const WIND_DRAG: f32 = 1.;
+const ROTATION_SPEED: f32 = 6.;
+
+/// Air in the floaty hat, used up by lift while ballooning
+#[derive(Component, Debug)]
+pub struct FloatyHat {
+    air: f32,
+}
+
+impl Default for FloatyHat {
+    fn default() -> Self {
+        Self { air: AIR_CAPACITY }
+    }
+}
+
+impl FloatyHat {
+    // 0 is empty, 1 is full
+    pub fn air(&self) -> f32 {
+        self.air / AIR_CAPACITY
+    }
+}
+
+/// On the player while it is in the floaty state
+#[derive(Component, Debug, Default)]
+pub struct Ballooning {
+    // horizontal velocity, kept between frames so the player drifts
+    drift: Vec3,
+    input: Vec3,
+}
+
+pub fn on_event(
+    In(event): In<StateEvent<PlayerEvent>>,
+    mut ballooning: Query<&mut Ballooning, With<Player>>,
     mut commands: Commands,
 ) {
-    match event.event() {
-        PlayerEvent::Floaty(event) => {
-            if !event.active {
-                new_state!(commands, fsm, current_state, super::idle_run::process_event);
+    match event.event {
+        PlayerEvent::Floaty(floaty) => {
+            if !floaty.active {
+                commands.set_state(event.entity, PlayerState::IdleRun);
+            }
+        }
+        PlayerEvent::Movement(movement) => {
+            if let Ok(mut ballooning) = ballooning.get_mut(event.entity) {
+                ballooning.input = movement.motion.unwrap_or_default();
             }
         }
         _ => (),
     }
 }
+
+/// Inflates the hat, keeping the horizontal speed the player had
+pub fn on_enter(
+    In(entity): In<Entity>,
+    players: Query<&CharacterController, With<Player>>,
+    mut im: ResMut<InputManager>,
+    mut commands: Commands,
+) {
+    im.consume_buffered_press(ABILITY_FLOATY);
+    let Ok(controller) = players.get(entity) else {
+        return;
+    };
+    let velocity = controller.velocity();
+    commands.entity(entity).insert(Ballooning {
+        drift: Vec3::new(velocity.x, 0., velocity.z),
+        input: Vec3::ZERO,
+    });
+}
+
+pub fn on_exit(In(entity): In<Entity>, mut commands: Commands) {
+    commands.entity(entity).remove::<Ballooning>();
+}
+
+/**
+ * Lift, slow descent and drift of the inflated hat, and refilling it on the ground.
+ * Runs before the character controller moves the player
+ */
+pub fn update_ballooning(
+    wind: Wind,
+    mut players: Query<
+        (
+            &mut Transform,
+            &mut CharacterController,
+            &mut FloatyHat,
+            Option<&mut Ballooning>,
+        ),
+        With<Player>,
+    >,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    for (mut transform, mut controller, mut hat, ballooning) in players.iter_mut() {
+        let Some(mut ballooning) = ballooning else {
+            controller.gravity_scale = 1.;
+            if controller.is_grounded() {
+                hat.air = (hat.air + AIR_REFILL * dt).min(AIR_CAPACITY);
+            }
+            continue;
+        };
+
+        let wind = wind.at(transform.translation) * WIND_DRAG;
+
+        let target = ballooning.input * AIR_SPEED + Vec3::new(wind.x, 0., wind.z);
+        let drift = ballooning.drift;
+        ballooning.drift = drift + (target - drift).clamp_length_max(AIR_ACCELERATION * dt);
+        controller.desired_velocity = ballooning.drift;
+
+        // the hat lifts while it has air, an updraft lifts up to its own speed even without
+        let mut max_rise = wind.y.max(0.);
+        if hat.air > 0. {
+            hat.air = (hat.air - dt).max(0.);
+            max_rise = max_rise.max(MAX_RISE_SPEED);
+        }
+        let mut vertical = controller.velocity().y;
+        if max_rise > 0. && vertical < max_rise {
+            vertical = (vertical + LIFT_ACCELERATION * dt).min(max_rise);
+        }
+        controller.gravity_scale = GRAVITY_SCALE;
+        controller.set_vertical_velocity(vertical.max(-MAX_FALL_SPEED));
+
+        if ballooning.input != Vec3::ZERO {
+            let input = ballooning.input;
+            movement::rotate_player(input, &mut transform, ROTATION_SPEED, &time);
+        }
+    }
+}
@@ -1,50 +1,74 @@
 use crate::{
-    new_state,
+    input::input_manager::InputManager,
     player::{
-        controller::{Player, PlayerEvent, PlayerFsm, PlayerMovementEvent},
+        character_controller::CharacterController,
+        controller::{
+            Player, PlayerCordyCeptEvent, PlayerEvent, PlayerFloatyEvent, PlayerMovementEvent,
+            ABILITY_CORDYCEPT, ABILITY_FLOATY,
+        },
         states::utils::movement,
     },
+    utils::fsm::{FsmCommandsExt, StateEvent},
 };
 use bevy::prelude::*;
 
+use super::PlayerState;
+
 const RUN_SPEED: f32 = 10.0;
 const ROTATION_SPEED: f32 = 22.0;
 
-pub fn process_event(
-    event: Trigger<PlayerEvent>,
-    fsm: Single<Entity, With<PlayerFsm>>,
-    current_state: Single<&Children, With<PlayerFsm>>,
+pub fn on_event(
+    In(event): In<StateEvent<PlayerEvent>>,
     mut commands: Commands,
-    mut transform: Single<&mut Transform, With<Player>>,
+    mut players: Query<(&mut Transform, &mut CharacterController), With<Player>>,
     time: Res<Time>,
 ) {
-    match event.event() {
-        PlayerEvent::Movement(event) => idle_run(&event, &mut *transform, &time),
-        PlayerEvent::Floaty(event) => {
-            if event.active {
-                new_state!(commands, fsm, current_state, super::floaty::process_event);
+    let Ok((mut transform, mut controller)) = players.get_mut(event.entity) else {
+        return;
+    };
+    match event.event {
+        PlayerEvent::Movement(movement) => {
+            idle_run(&movement, &mut transform, &mut controller, &time)
+        }
+        // the entered state consumes the buffered press, a denied transition leaves it buffered
+        PlayerEvent::Floaty(floaty) => {
+            if floaty.active {
+                commands.set_state(event.entity, PlayerState::Floaty);
             }
         }
-        PlayerEvent::CordyCept(event) => {
-            if event.active {
-                new_state!(
-                    commands,
-                    fsm,
-                    current_state,
-                    super::cordycept::process_event
-                );
+        PlayerEvent::CordyCept(cordycept) => {
+            if cordycept.active {
+                commands.set_state(event.entity, PlayerState::CordyCept);
             }
         }
     }
 }
 
-fn idle_run(event: &PlayerMovementEvent, transform: &mut Transform, time: &Time) {
+/// Picks up an ability pressed while the previous state was being left
+pub fn on_enter(_: In<Entity>, mut im: ResMut<InputManager>, mut commands: Commands) {
+    // only while still held, the release is already gone and would leave the ability on
+    if im.is_action_pressed(ABILITY_FLOATY) && im.consume_buffered_press(ABILITY_FLOATY) {
+        commands.trigger(PlayerEvent::Floaty(PlayerFloatyEvent { active: true }));
+    } else if im.is_action_pressed(ABILITY_CORDYCEPT)
+        && im.consume_buffered_press(ABILITY_CORDYCEPT)
+    {
+        commands.trigger(PlayerEvent::CordyCept(PlayerCordyCeptEvent {
+            active: true,
+        }));
+    }
+}
+
+fn idle_run(
+    event: &PlayerMovementEvent,
+    transform: &mut Transform,
+    controller: &mut CharacterController,
+    time: &Time,
+) {
     let Some(motion) = event.motion else {
         return;
     };
 
-    let movement = motion * RUN_SPEED * time.delta_secs();
-    transform.translation += movement;
+    controller.desired_velocity = motion * RUN_SPEED;
 
     movement::rotate_player(motion, transform, ROTATION_SPEED, time);
 }
@@ -1,4 +1,12 @@
 pub mod idle_run;
 pub mod floaty;
 pub mod cordycept;
-mod utils;
\ No newline at end of file
+mod utils;
+
+/// States of the player state machine, the hooks of each live in the module of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlayerState {
+    IdleRun,
+    Floaty,
+    CordyCept,
+}
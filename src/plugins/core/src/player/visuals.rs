@@ -1,8 +1,20 @@
 use std::time::Duration;
 
-use bevy::prelude::*;
+use bevy::{prelude::*, scene::SceneInstanceReady, utils::HashMap};
 
-use super::controller::{Player, PlayerEvent};
+use super::{character_controller::CharacterController, controller::Player, states::PlayerState};
+use crate::utils::fsm::{StateChanged, StateMachine};
+
+// These match index of array in Animation graph, not .gltf file
+pub const ANIM_IDLE: usize = 1;
+pub const ANIM_RUN: usize = 0;
+pub const ANIM_FLOATY_HAT: usize = 2;
+pub const ANIM_BLAST: usize = 3;
+
+// playback speed of the run per unit of horizontal speed
+const ANIMSPEED_RUN: f32 = 0.8;
+// horizontal speed below which a locomotion blend counts as standing
+const MOVING_THRESHOLD: f32 = 0.1;
 
 #[derive(Resource)]
 pub struct Animations {
@@ -10,15 +22,94 @@ pub struct Animations {
     graph: Handle<AnimationGraph>,
 }
 
-#[derive(Component)]
-pub struct AnimationComponent {
-    pub fsm: fsm::Fsm,
+/// Which clips a state plays, by index into Animations
+#[derive(Debug, Clone, Copy)]
+pub enum StateBlend {
+    Clip(usize),
+    // idle while standing, moving at a playback speed scaled by the horizontal speed otherwise
+    Locomotion {
+        idle: usize,
+        moving: usize,
+        speed_scale: f32,
+    },
+}
+
+impl StateBlend {
+    fn clip(&self, speed: f32) -> usize {
+        match *self {
+            Self::Clip(clip) => clip,
+            Self::Locomotion { idle, moving, .. } => {
+                if speed > MOVING_THRESHOLD {
+                    moving
+                } else {
+                    idle
+                }
+            }
+        }
+    }
+
+    fn playback_speed(&self, speed: f32) -> f32 {
+        match *self {
+            Self::Locomotion { speed_scale, .. } if speed > MOVING_THRESHOLD => speed * speed_scale,
+            _ => 1.,
+        }
+    }
+}
+
+/// Animation of a player state, cross_fade is used both entering the state and within its blend
+#[derive(Debug, Clone, Copy)]
+pub struct StateAnimation {
+    pub blend: StateBlend,
+    pub cross_fade: Duration,
+}
+
+/**
+ * Animation of every player state, the animation follows the gameplay state machine.
+ * New abilities get their clip here next to their state
+ */
+#[derive(Resource, Debug)]
+pub struct StateAnimations(pub HashMap<PlayerState, StateAnimation>);
+
+impl Default for StateAnimations {
+    fn default() -> Self {
+        Self(HashMap::from([
+            (
+                PlayerState::IdleRun,
+                StateAnimation {
+                    blend: StateBlend::Locomotion {
+                        idle: ANIM_IDLE,
+                        moving: ANIM_RUN,
+                        speed_scale: ANIMSPEED_RUN,
+                    },
+                    cross_fade: Duration::from_millis(150),
+                },
+            ),
+            (
+                PlayerState::Floaty,
+                StateAnimation {
+                    blend: StateBlend::Clip(ANIM_FLOATY_HAT),
+                    cross_fade: Duration::from_millis(100),
+                },
+            ),
+            (
+                PlayerState::CordyCept,
+                StateAnimation {
+                    blend: StateBlend::Clip(ANIM_BLAST),
+                    cross_fade: Duration::from_millis(100),
+                },
+            ),
+        ]))
+    }
 }
 
 #[derive(Component)]
 pub struct Boxy;
 const BOXY_PATH: &str = "models/boxy.glb";
 
+/// The AnimationPlayer in the player's scene, set up by setup_once_loaded
+#[derive(Component)]
+pub struct PlayerAnimationPlayer;
+
 pub(super) fn spawn_player_mesh(
     _: Trigger<OnAdd, Player>,
     player: Single<Entity, With<Player>>,
@@ -42,200 +133,135 @@ pub(super) fn spawn_player_mesh(
     commands.entity(*player).insert((
         SceneRoot(asset_server.load(GltfAssetLabel::Scene(0).from_asset(BOXY_PATH))),
         Boxy,
-        AnimationComponent {
-            fsm: fsm::Fsm::new(),
-        },
     ));
 }
 
 pub(super) fn setup_once_loaded(
-    _: Trigger<OnInsert, AnimationPlayer>,
-    player: Single<Entity, With<Player>>,
+    trigger: Trigger<SceneInstanceReady>,
+    player: Single<(Entity, &StateMachine<PlayerState>, &CharacterController), With<Player>>,
+    children: Query<&Children>,
     mut commands: Commands,
     animations: Res<Animations>,
-    mut anim_players: Query<(Entity, &mut AnimationPlayer), With<AnimationPlayer>>,
+    state_animations: Res<StateAnimations>,
+    mut anim_players: Query<&mut AnimationPlayer>,
 ) {
-    println!("AnimationPlayer loaded...");
-    let (entity, mut anim_player) = anim_players.single_mut();
-    println!("player {:?}", *player);
-    println!("entt {:?}", entity);
+    let (player, machine, controller) = player.into_inner();
+    // scenes of other entities, e.g. animated enemies, have their own animation players
+    if trigger.entity() != player {
+        return;
+    }
+    let Some(entity) = children
+        .iter_descendants(player)
+        .find(|entity| anim_players.contains(*entity))
+    else {
+        return;
+    };
+    let Ok(mut anim_player) = anim_players.get_mut(entity) else {
+        return;
+    };
+    // the state was entered before the scene loaded, start in it right away
     let mut transitions = AnimationTransitions::new();
-    transitions
-        .play(
-            &mut anim_player,
-            animations.animations[fsm::ANIM_IDLE],
+    if let Some(animation) = state_animations.0.get(&machine.current()) {
+        play_state_animation(
+            animation,
+            horizontal_speed(controller),
             Duration::ZERO,
-        )
-        .repeat();
+            &animations,
+            &mut anim_player,
+            &mut transitions,
+        );
+    }
     commands
         .entity(entity)
         .insert(AnimationGraphHandle(animations.graph.clone()))
-        .insert(transitions);
-    commands.entity(*player).insert_children(0, &[entity]);
+        .insert((transitions, PlayerAnimationPlayer));
+    commands.entity(player).insert_children(0, &[entity]);
 }
 
-pub(super) fn observe_player_event(
-    event: Trigger<PlayerEvent>,
-    _: Query<&Parent, With<Player>>,
-    mut fsm: Single<&mut AnimationComponent, With<Player>>,
-    mut animation_players: Query<(&mut AnimationPlayer, &mut AnimationTransitions)>,
-    animations: Res<Animations>,
+/// Cross-fades to the animation of the state the player just entered
+pub(super) fn observe_state_changed(
+    event: Trigger<StateChanged<PlayerState>>,
+    players: Query<&CharacterController, With<Player>>,
+    animations: Option<Res<Animations>>,
+    state_animations: Res<StateAnimations>,
+    mut animation_players: Query<
+        (&mut AnimationPlayer, &mut AnimationTransitions),
+        With<PlayerAnimationPlayer>,
+    >,
 ) {
-    let (mut player, mut transitions) = animation_players.single_mut();
-    fsm.fsm.process_event(
-        &event.event(),
-        &mut fsm::AnimUpdateAggregate(&animations, &mut player, &mut transitions),
+    // before the scene is loaded setup_once_loaded starts the animation instead
+    let (Some(animations), Ok((mut anim_player, mut transitions))) =
+        (animations, animation_players.get_single_mut())
+    else {
+        return;
+    };
+    let Some(animation) = state_animations.0.get(&event.event().to) else {
+        return;
+    };
+    let speed = players
+        .get(event.entity())
+        .map(horizontal_speed)
+        .unwrap_or_default();
+    play_state_animation(
+        animation,
+        speed,
+        animation.cross_fade,
+        &animations,
+        &mut anim_player,
+        &mut transitions,
     );
 }
 
-mod fsm {
-    use std::time::Duration;
-
-    use bevy::prelude::*;
-
-    use crate::player::controller::{PlayerEvent, PlayerMovementEvent};
-    use crate::player::visuals::Animations;
-
-    // These match index of array in Animation graph, not .gltf file
-    pub const ANIM_IDLE: usize = 1;
-    pub const ANIM_RUN: usize = 0;
-    pub const ANIM_FLOATY_HAT: usize = 2;
-    pub const ANIM_BLAST: usize = 3;
-
-    const ANIMSPEED_RUN: f32 = 8.0;
-
-    pub struct AnimUpdateAggregate<'a>(
-        pub &'a Animations,
-        pub &'a mut AnimationPlayer,
-        pub &'a mut AnimationTransitions,
-    );
-    pub struct StateMovement;
-    pub struct StateFloaty;
-    pub struct Fsm {
-        current_state: Box<dyn TState>,
-    }
-    impl Fsm {
-        pub fn new() -> Self {
-            Self {
-                current_state: Box::new(StateMovement),
-            }
-        }
-
-        pub fn process_event(
-            &mut self,
-            event: &PlayerEvent,
-            anim_update: &mut AnimUpdateAggregate,
-        ) {
-            let new_state = self.current_state.process_event(event, anim_update);
-
-            let Some(new_state) = new_state else {
-                return;
-            };
-
-            self.current_state = new_state;
-            self.current_state.enter_state(event, anim_update);
-        }
-    }
-
-    pub trait TState: Send + Sync {
-        fn enter_state(&self, event: &PlayerEvent, anim_update: &mut AnimUpdateAggregate);
-        fn process_event(
-            &self,
-            event: &PlayerEvent,
-            anim_update: &mut AnimUpdateAggregate,
-        ) -> Option<Box<dyn TState>>;
-    }
-
-    impl TState for StateMovement {
-        fn enter_state(&self, event: &PlayerEvent, anim_update: &mut AnimUpdateAggregate) {
-            if let PlayerEvent::Movement(event) = event {
-                self.handle_movement(event, anim_update);
-            } else {
-                self.handle_movement(&PlayerMovementEvent::empty(), anim_update);
-            }
-        }
-
-        fn process_event(
-            &self,
-            event: &PlayerEvent,
-            anim_update: &mut AnimUpdateAggregate,
-        ) -> Option<Box<dyn TState>> {
-            if let PlayerEvent::Movement(event) = event {
-                self.handle_movement(event, anim_update);
-                return None;
-            }
-
-            match event {
-                PlayerEvent::Floaty(_) => Some(Box::new(StateFloaty)),
-                _ => None,
-            }
-        }
+/// Follows the blend of the current state, e.g. between idle and run
+pub(super) fn update_state_blend(
+    player: Option<Single<(&StateMachine<PlayerState>, &CharacterController), With<Player>>>,
+    animations: Option<Res<Animations>>,
+    state_animations: Res<StateAnimations>,
+    mut animation_players: Query<
+        (&mut AnimationPlayer, &mut AnimationTransitions),
+        With<PlayerAnimationPlayer>,
+    >,
+) {
+    let (Some(player), Some(animations), Ok((mut anim_player, mut transitions))) =
+        (player, animations, animation_players.get_single_mut())
+    else {
+        return;
+    };
+    let (machine, controller) = player.into_inner();
+    let Some(animation) = state_animations.0.get(&machine.current()) else {
+        return;
+    };
+    let speed = horizontal_speed(controller);
+    let node = animations.animations[animation.blend.clip(speed)];
+    if transitions.get_main_animation() != Some(node) {
+        play_state_animation(
+            animation,
+            speed,
+            animation.cross_fade,
+            &animations,
+            &mut anim_player,
+            &mut transitions,
+        );
+    } else if let Some(active) = anim_player.animation_mut(node) {
+        active.set_speed(animation.blend.playback_speed(speed));
     }
-    impl TState for StateFloaty {
-        fn enter_state(&self, event: &PlayerEvent, anim_update: &mut AnimUpdateAggregate) {
-            let PlayerEvent::Floaty(event) = event else {
-                return;
-            };
-
-            if event.active {
-                let AnimUpdateAggregate(animations, anim_player, anim_transitions) =
-                    &mut *anim_update;
-                anim_transitions
-                    .play(
-                        anim_player,
-                        animations.animations[ANIM_FLOATY_HAT],
-                        Duration::ZERO,
-                    )
-                    .repeat();
-            }
-        }
+}
 
-        fn process_event(
-            &self,
-            event: &PlayerEvent,
-            _anim_update: &mut AnimUpdateAggregate,
-        ) -> Option<Box<dyn TState>> {
-            match event {
-                PlayerEvent::Floaty(event) => {
-                    if !event.active {
-                        return Some(Box::new(StateMovement));
-                    }
-                    None
-                }
-                _ => None,
-            }
-        }
-    }
+fn play_state_animation(
+    animation: &StateAnimation,
+    speed: f32,
+    cross_fade: Duration,
+    animations: &Animations,
+    anim_player: &mut AnimationPlayer,
+    transitions: &mut AnimationTransitions,
+) {
+    let node = animations.animations[animation.blend.clip(speed)];
+    transitions
+        .play(anim_player, node, cross_fade)
+        .set_speed(animation.blend.playback_speed(speed))
+        .repeat();
+}
 
-    impl StateMovement {
-        fn handle_movement(
-            &self,
-            movement_event: &PlayerMovementEvent,
-            anim_update: &mut AnimUpdateAggregate,
-        ) {
-            let AnimUpdateAggregate(animations, anim_player, anim_transitions) = &mut *anim_update;
-
-            match movement_event.motion {
-                Some(delta) => {
-                    if !anim_player.is_playing_animation(animations.animations[ANIM_RUN]) {
-                        anim_transitions
-                            .play(anim_player, animations.animations[ANIM_RUN], Duration::ZERO)
-                            .repeat();
-                    }
-                    for (_, anim) in anim_player.playing_animations_mut() {
-                        anim.set_speed(delta.length() * ANIMSPEED_RUN);
-                    }
-                }
-                None => {
-                    anim_transitions
-                        .play(
-                            anim_player,
-                            animations.animations[ANIM_IDLE],
-                            Duration::ZERO,
-                        )
-                        .repeat();
-                }
-            }
-        }
-    }
+fn horizontal_speed(controller: &CharacterController) -> f32 {
+    controller.velocity().xz().length()
 }
@@ -1,5 +1,5 @@
 use crate::{
-    input::input_manager::{button, Action, InputManager},
+    input::input_manager::{button, contexts::InputContext, Action, InputManager},
     settings::title::Title,
 };
 use bevy::{
@@ -53,6 +53,13 @@ fn startup(mut settings: ResMut<FramepaceSettings>) {
 }
 
 fn register_input(mut input: ResMut<InputManager>) {
+    for action in [
+        ACTION_INCREASE_FRAMERATE,
+        ACTION_DECREASE_FRAMERATE,
+        ACTION_SHOW_FPS,
+    ] {
+        input.set_action_context(action, InputContext::GLOBAL);
+    }
     input.register_action_button(
         ACTION_INCREASE_FRAMERATE,
         vec![button::Variant::Keyboard(KeyCode::Equal)],
@@ -1,24 +1,458 @@
+use std::{fmt::Debug, hash::Hash};
+
+use bevy::{ecs::system::SystemId, prelude::*, utils::HashMap};
 
 /**
  * Basis of FSM system
- * Expects an entity hierarchy of
- * <main_entity>/<fsm_entity>/<current_state>
- * with this macro changing the current state
- * 
- * each state is an observer
- * 
- * Query example:
- * fsm: Single<Entity, With<PlayerFsm>>,
- * current_state: Single<&Children, With<PlayerFsm>>,
- * mut commands: Commands,
+ * A state is identified by a value of S, usually a fieldless enum with one variant per state.
+ * An entity gets a state machine with the StateMachine<S> component, which is also where
+ * its current state is queried from, and changes state with Commands::set_state.
+ *
+ * Behaviour is registered per state on the App, each hook is a system with the entity as input:
+ * app.add_state_enter(S::A, enter_a)                    In<Entity>
+ *     .add_state_exit(S::A, exit_a)                     In<Entity>
+ *     .add_state_event(S::A, on_event_a)                In<StateEvent<E>>, E is a triggered event
+ *     .add_transition_guard(Some(S::A), S::B, a_to_b)   In<Transition<S>> -> bool
+ *
+ * A transition runs the guards, the exit hooks of the old state, triggers StateChanged<S>
+ * targeting the entity and then runs the enter hooks of the new state
  */
-#[macro_export]
-macro_rules! new_state {
-    ($commands:expr, $fsm:expr, $children:expr, $next_state:expr) => {{
-        for c in *$children {
-            $commands.entity(*c).remove_parent().despawn();
+pub trait FsmState: Copy + Eq + Hash + Debug + Send + Sync + 'static {}
+impl<T: Copy + Eq + Hash + Debug + Send + Sync + 'static> FsmState for T {}
+
+/// Current state of an entity, the enter hooks of the initial state run once it is added
+#[derive(Component, Debug)]
+pub struct StateMachine<S: FsmState> {
+    current: S,
+    previous: Option<S>,
+}
+
+impl<S: FsmState> StateMachine<S> {
+    pub fn new(initial: S) -> Self {
+        Self {
+            current: initial,
+            previous: None,
+        }
+    }
+
+    pub fn current(&self) -> S {
+        self.current
+    }
+
+    pub fn previous(&self) -> Option<S> {
+        self.previous
+    }
+
+    pub fn is(&self, state: S) -> bool {
+        self.current == state
+    }
+}
+
+/**
+ * Triggered targeting the entity once it is in the new state, before the enter hooks run.
+ * from is None when the initial state is entered
+ */
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StateChanged<S: FsmState> {
+    pub from: Option<S>,
+    pub to: S,
+}
+
+/// Transition about to happen, what the guards decide on
+#[derive(Debug, Clone, Copy)]
+pub struct Transition<S: FsmState> {
+    pub entity: Entity,
+    pub from: S,
+    pub to: S,
+}
+
+/// Triggered event handed to the current state of an entity
+#[derive(Debug, Clone)]
+pub struct StateEvent<E> {
+    pub entity: Entity,
+    pub event: E,
+}
+
+type StateHook = SystemId<In<Entity>>;
+type TransitionGuard<S> = SystemId<In<Transition<S>>, bool>;
+type EventHook<E> = SystemId<In<StateEvent<E>>>;
+
+#[derive(Resource)]
+struct StateHooks<S: FsmState> {
+    on_enter: HashMap<S, Vec<StateHook>>,
+    on_exit: HashMap<S, Vec<StateHook>>,
+    // a guard without a from state guards every transition to its state
+    guards: Vec<(Option<S>, S, TransitionGuard<S>)>,
+}
+
+impl<S: FsmState> Default for StateHooks<S> {
+    fn default() -> Self {
+        Self {
+            on_enter: HashMap::default(),
+            on_exit: HashMap::default(),
+            guards: Vec::new(),
         }
-        let new_state = $commands.add_observer($next_state).id();
-        $commands.entity(*$fsm).insert_children(0, &[new_state]);
-    }};
+    }
+}
+
+#[derive(Resource)]
+struct EventHooks<S: FsmState, E: Event> {
+    hooks: HashMap<S, Vec<EventHook<E>>>,
+}
+
+pub trait FsmAppExt {
+    /// Sets up the state machines of S, the add_ functions below do this as well
+    fn add_state_machine<S: FsmState>(&mut self) -> &mut Self;
+
+    fn add_state_enter<S: FsmState, M>(
+        &mut self,
+        state: S,
+        hook: impl IntoSystem<In<Entity>, (), M> + 'static,
+    ) -> &mut Self;
+
+    fn add_state_exit<S: FsmState, M>(
+        &mut self,
+        state: S,
+        hook: impl IntoSystem<In<Entity>, (), M> + 'static,
+    ) -> &mut Self;
+
+    /**
+     * Hands every triggered E to the entities currently in the state,
+     * an E triggered for a target only to that entity
+     */
+    fn add_state_event<S: FsmState, E: Event + Clone, M>(
+        &mut self,
+        state: S,
+        hook: impl IntoSystem<In<StateEvent<E>>, (), M> + 'static,
+    ) -> &mut Self;
+
+    /// The transition only happens if every guard matching it returns true
+    fn add_transition_guard<S: FsmState, M>(
+        &mut self,
+        from: Option<S>,
+        to: S,
+        guard: impl IntoSystem<In<Transition<S>>, bool, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl FsmAppExt for App {
+    fn add_state_machine<S: FsmState>(&mut self) -> &mut Self {
+        if !self.world().contains_resource::<StateHooks<S>>() {
+            self.init_resource::<StateHooks<S>>()
+                .add_observer(enter_initial_state::<S>);
+        }
+        self
+    }
+
+    fn add_state_enter<S: FsmState, M>(
+        &mut self,
+        state: S,
+        hook: impl IntoSystem<In<Entity>, (), M> + 'static,
+    ) -> &mut Self {
+        self.add_state_machine::<S>();
+        let hook = self.world_mut().register_system(hook);
+        self.world_mut()
+            .resource_mut::<StateHooks<S>>()
+            .on_enter
+            .entry(state)
+            .or_default()
+            .push(hook);
+        self
+    }
+
+    fn add_state_exit<S: FsmState, M>(
+        &mut self,
+        state: S,
+        hook: impl IntoSystem<In<Entity>, (), M> + 'static,
+    ) -> &mut Self {
+        self.add_state_machine::<S>();
+        let hook = self.world_mut().register_system(hook);
+        self.world_mut()
+            .resource_mut::<StateHooks<S>>()
+            .on_exit
+            .entry(state)
+            .or_default()
+            .push(hook);
+        self
+    }
+
+    fn add_state_event<S: FsmState, E: Event + Clone, M>(
+        &mut self,
+        state: S,
+        hook: impl IntoSystem<In<StateEvent<E>>, (), M> + 'static,
+    ) -> &mut Self {
+        self.add_state_machine::<S>();
+        if !self.world().contains_resource::<EventHooks<S, E>>() {
+            self.insert_resource(EventHooks::<S, E> {
+                hooks: HashMap::default(),
+            })
+            .add_observer(dispatch_event::<S, E>);
+        }
+        let hook = self.world_mut().register_system(hook);
+        self.world_mut()
+            .resource_mut::<EventHooks<S, E>>()
+            .hooks
+            .entry(state)
+            .or_default()
+            .push(hook);
+        self
+    }
+
+    fn add_transition_guard<S: FsmState, M>(
+        &mut self,
+        from: Option<S>,
+        to: S,
+        guard: impl IntoSystem<In<Transition<S>>, bool, M> + 'static,
+    ) -> &mut Self {
+        self.add_state_machine::<S>();
+        let guard = self.world_mut().register_system(guard);
+        self.world_mut()
+            .resource_mut::<StateHooks<S>>()
+            .guards
+            .push((from, to, guard));
+        self
+    }
+}
+
+pub trait FsmCommandsExt {
+    /// Moves the state machine of the entity to the state, unless a guard denies it
+    fn set_state<S: FsmState>(&mut self, entity: Entity, state: S);
+}
+
+impl FsmCommandsExt for Commands<'_, '_> {
+    fn set_state<S: FsmState>(&mut self, entity: Entity, state: S) {
+        self.queue(move |world: &mut World| transition::<S>(world, entity, state));
+    }
+}
+
+fn transition<S: FsmState>(world: &mut World, entity: Entity, to: S) {
+    let Some(from) = world
+        .get::<StateMachine<S>>(entity)
+        .map(|machine| machine.current)
+    else {
+        return;
+    };
+    if from == to {
+        return;
+    }
+    let transition = Transition { entity, from, to };
+
+    let (guards, on_exit) = world
+        .get_resource::<StateHooks<S>>()
+        .map(|hooks| {
+            let guards = hooks
+                .guards
+                .iter()
+                .filter(|(guard_from, guard_to, _)| {
+                    *guard_to == to && guard_from.is_none_or(|guard_from| guard_from == from)
+                })
+                .map(|(_, _, guard)| *guard)
+                .collect::<Vec<_>>();
+            (
+                guards,
+                hooks.on_exit.get(&from).cloned().unwrap_or_default(),
+            )
+        })
+        .unwrap_or_default();
+
+    for guard in guards {
+        match world.run_system_with_input(guard, transition) {
+            Ok(true) => (),
+            Ok(false) => return,
+            Err(err) => {
+                warn!("Transition guard {:?} -> {:?} failed: {}", from, to, err);
+                return;
+            }
+        }
+    }
+
+    run_hooks(world, entity, on_exit);
+    if let Some(mut machine) = world.get_mut::<StateMachine<S>>(entity) {
+        machine.previous = Some(from);
+        machine.current = to;
+    }
+    enter_state(world, entity, Some(from), to);
+}
+
+fn enter_state<S: FsmState>(world: &mut World, entity: Entity, from: Option<S>, to: S) {
+    world.trigger_targets(StateChanged { from, to }, entity);
+    let on_enter = world
+        .get_resource::<StateHooks<S>>()
+        .and_then(|hooks| hooks.on_enter.get(&to).cloned())
+        .unwrap_or_default();
+    run_hooks(world, entity, on_enter);
+}
+
+fn run_hooks(world: &mut World, entity: Entity, hooks: Vec<StateHook>) {
+    for hook in hooks {
+        if let Err(err) = world.run_system_with_input(hook, entity) {
+            warn!("State hook failed: {}", err);
+        }
+    }
+}
+
+fn enter_initial_state<S: FsmState>(
+    trigger: Trigger<OnAdd, StateMachine<S>>,
+    mut commands: Commands,
+) {
+    let entity = trigger.entity();
+    commands.queue(move |world: &mut World| {
+        let Some(initial) = world
+            .get::<StateMachine<S>>(entity)
+            .map(|machine| machine.current)
+        else {
+            return;
+        };
+        enter_state(world, entity, None, initial);
+    });
+}
+
+fn dispatch_event<S: FsmState, E: Event + Clone>(
+    trigger: Trigger<E>,
+    machines: Query<(Entity, &StateMachine<S>)>,
+    hooks: Res<EventHooks<S, E>>,
+    mut commands: Commands,
+) {
+    let target = trigger.entity();
+    for (entity, machine) in machines
+        .iter()
+        .filter(|(entity, _)| target == Entity::PLACEHOLDER || *entity == target)
+    {
+        for hook in hooks.hooks.get(&machine.current).into_iter().flatten() {
+            commands.run_system_with_input(
+                *hook,
+                StateEvent {
+                    entity,
+                    event: trigger.event().clone(),
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum TestState {
+        A,
+        B,
+        C,
+    }
+
+    #[derive(Event, Clone)]
+    struct Go;
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    #[derive(Resource, Default)]
+    struct AllowC(bool);
+
+    fn log(world: &World) -> Vec<String> {
+        world.resource::<Log>().0.clone()
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Log>()
+            .init_resource::<AllowC>()
+            .add_state_enter(TestState::A, |_: In<Entity>, mut log: ResMut<Log>| {
+                log.0.push("enter A".into())
+            })
+            .add_state_exit(TestState::A, |_: In<Entity>, mut log: ResMut<Log>| {
+                log.0.push("exit A".into())
+            })
+            .add_state_enter(TestState::B, |_: In<Entity>, mut log: ResMut<Log>| {
+                log.0.push("enter B".into())
+            })
+            .add_state_event(
+                TestState::A,
+                |In(event): In<StateEvent<Go>>, mut commands: Commands| {
+                    commands.set_state(event.entity, TestState::B)
+                },
+            )
+            .add_transition_guard(
+                None,
+                TestState::C,
+                |_: In<Transition<TestState>>, allow: Res<AllowC>| allow.0,
+            )
+            .add_observer(
+                |trigger: Trigger<StateChanged<TestState>>, mut log: ResMut<Log>| {
+                    let StateChanged { from, to } = *trigger.event();
+                    log.0.push(format!("changed {from:?} -> {to:?}"));
+                },
+            );
+        app
+    }
+
+    fn state(world: &World, entity: Entity) -> (TestState, Option<TestState>) {
+        let machine = world.get::<StateMachine<TestState>>(entity).unwrap();
+        (machine.current(), machine.previous())
+    }
+
+    #[test]
+    fn enters_initial_state() {
+        let mut app = app();
+        let world = app.world_mut();
+        world.spawn(StateMachine::new(TestState::A));
+        world.flush();
+        assert_eq!(log(world), ["changed None -> A", "enter A"]);
+    }
+
+    #[test]
+    fn events_go_to_the_current_state() {
+        let mut app = app();
+        let world = app.world_mut();
+        let entity = world.spawn(StateMachine::new(TestState::A)).id();
+        world.flush();
+        world.resource_mut::<Log>().0.clear();
+
+        world.trigger(Go);
+        world.flush();
+        assert_eq!(state(world, entity), (TestState::B, Some(TestState::A)));
+        assert_eq!(log(world), ["exit A", "changed Some(A) -> B", "enter B"]);
+
+        // B has no handler for Go
+        world.resource_mut::<Log>().0.clear();
+        world.trigger(Go);
+        world.flush();
+        assert_eq!(state(world, entity), (TestState::B, Some(TestState::A)));
+        assert!(log(world).is_empty());
+    }
+
+    #[test]
+    fn targeted_events_go_to_their_entity() {
+        let mut app = app();
+        let world = app.world_mut();
+        let target = world.spawn(StateMachine::new(TestState::A)).id();
+        let other = world.spawn(StateMachine::new(TestState::A)).id();
+        world.flush();
+
+        world.trigger_targets(Go, target);
+        world.flush();
+        assert_eq!(state(world, target), (TestState::B, Some(TestState::A)));
+        assert_eq!(state(world, other), (TestState::A, None));
+    }
+
+    #[test]
+    fn guards_deny_transitions() {
+        let mut app = app();
+        let world = app.world_mut();
+        let entity = world.spawn(StateMachine::new(TestState::A)).id();
+        world.flush();
+        world.resource_mut::<Log>().0.clear();
+
+        world.commands().set_state(entity, TestState::C);
+        world.flush();
+        assert_eq!(state(world, entity), (TestState::A, None));
+        assert!(log(world).is_empty());
+
+        world.resource_mut::<AllowC>().0 = true;
+        world.commands().set_state(entity, TestState::C);
+        world.flush();
+        assert_eq!(state(world, entity), (TestState::C, Some(TestState::A)));
+        assert_eq!(log(world), ["exit A", "changed Some(A) -> C"]);
+    }
 }
@@ -1,2 +1,3 @@
 pub(crate) mod collision;
 pub(crate) mod fsm;
+pub(crate) mod noise;
@@ -0,0 +1,16 @@
+use bevy::prelude::*;
+
+// smooth value noise in -1..1, seed picks an independent channel
+pub fn value_noise(t: f32, seed: u32) -> f32 {
+    fn hash(n: i32, seed: u32) -> f32 {
+        let mut x = (n as u32).wrapping_mul(0x27d4_eb2d) ^ seed.wrapping_mul(0x9e37_79b9);
+        x ^= x >> 15;
+        x = x.wrapping_mul(0x2c1b_3c6d);
+        x ^= x >> 12;
+        x as f32 / u32::MAX as f32 * 2. - 1.
+    }
+    let i = t.floor();
+    let f = t - i;
+    let smooth = f * f * (3. - 2. * f);
+    hash(i as i32, seed).lerp(hash(i as i32 + 1, seed), smooth)
+}